@@ -3,8 +3,13 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use cw_bundler::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
-use cw_bundler::state::State;
+use cw_bundler::msg::{
+    AssetListResponse, BundleContentsResponse, BundleContributorsResponse,
+    BundleDepositorsResponse, BundleInfoResponse, BundleLockResponse, BundlesInfoResponse,
+    BundlesResponse, DepositMsg, ExecuteMsg, InstantiateMsg, MigrateMsg, PausedResponse,
+    PendingTransfersResponse, QueryMsg, ReceiveMsg, RoleMembersResponse,
+};
+use cw_bundler::state::{Config, FeeConfig};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -14,6 +19,21 @@ fn main() {
 
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
+    export_schema(&schema_for!(DepositMsg), &out_dir);
+    export_schema(&schema_for!(ReceiveMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
-    export_schema(&schema_for!(State), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
+    export_schema(&schema_for!(Config), &out_dir);
+    export_schema(&schema_for!(FeeConfig), &out_dir);
+    export_schema(&schema_for!(PausedResponse), &out_dir);
+    export_schema(&schema_for!(RoleMembersResponse), &out_dir);
+    export_schema(&schema_for!(PendingTransfersResponse), &out_dir);
+    export_schema(&schema_for!(AssetListResponse), &out_dir);
+    export_schema(&schema_for!(BundleLockResponse), &out_dir);
+    export_schema(&schema_for!(BundleDepositorsResponse), &out_dir);
+    export_schema(&schema_for!(BundleContributorsResponse), &out_dir);
+    export_schema(&schema_for!(BundlesResponse), &out_dir);
+    export_schema(&schema_for!(BundleContentsResponse), &out_dir);
+    export_schema(&schema_for!(BundleInfoResponse), &out_dir);
+    export_schema(&schema_for!(BundlesInfoResponse), &out_dir);
 }
@@ -1,157 +1,434 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_binary, Addr, Binary, CosmosMsg, Deps, DepsMut, Empty, Env, MessageInfo, Response,
-    StdResult, Uint128, WasmMsg,
+    attr, to_binary, Addr, Attribute, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Empty, Env,
+    Event, MessageInfo, Order, Reply, Response, StdError, StdResult, Storage, SubMsg, Uint128,
+    WasmMsg,
 };
-use cw2::set_contract_version;
-use cw_storage_plus::Map;
+use cw2::{get_contract_version, set_contract_version};
+use cw_storage_plus::{Bound, PrimaryKey, U64Key};
 
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
 
-use cw721::{Cw721ExecuteMsg, Cw721ReceiveMsg};
+use cw721::{
+    Cw721ExecuteMsg, Cw721Query, Cw721QueryMsg, Cw721ReceiveMsg, Expiration, OwnerOfResponse,
+};
 use cw721_base::msg::{
-    ExecuteMsg as cw721_execute_msg, InstantiateMsg, QueryMsg as cw721_query_msg,
+    ExecuteMsg as cw721_execute_msg, InstantiateMsg as cw721_instantiate_msg,
+    QueryMsg as cw721_query_msg,
 };
 use cw721_base::state::TokenInfo;
 use cw721_base::{Cw721Contract, Extension};
 
-use cw1155::{Cw1155BatchReceiveMsg, Cw1155ExecuteMsg};
+use cw1155::{Cw1155BatchReceiveMsg, Cw1155ExecuteMsg, Cw1155ReceiveMsg};
 
 use crate::error::ContractError;
+use crate::migrations;
 use crate::msg::MintMsg;
-use crate::msg::{ExecuteMsg, QueryMsg};
+use crate::msg::{
+    AssetListResponse, BundleAssets, BundleContentsResponse, BundleContributorsResponse,
+    BundleDepositorsResponse, BundleInfoResponse, BundleLockResponse, BundleSplit,
+    BundlesInfoResponse, BundlesResponse, Contributor, DepositMsg, ExecuteMsg, InstantiateMsg,
+    MigrateMsg, NestedBundle, PausedResponse, PendingTransfersResponse, Price, QueryMsg,
+    ReceiveMsg, RoleMembersResponse, WithdrawOptions,
+};
+use crate::state::{
+    bundle_id_from_key, cw1155_bundle, cw20_bundle, cw721_bundle, AssetFilter, BundleLock,
+    CW1155Wrapper, CW20Wrapper, CW721Wrapper, Config, FeeConfig, MintPolicy, MintPrice,
+    PendingAsset, PendingDeposit, Role, ALLOWED_DEPOSITORS, ASSET_COUNTS, ASSET_LIST, BUNDLE_COUNT,
+    BUNDLE_CREATORS, CONFIG, CONTRIBUTORS, CREATED_BUNDLES, DEFAULT_MAX_BUNDLE_ASSETS,
+    DEFAULT_MAX_NESTING_DEPTH, LOCKS, NATIVE_BUNDLE, OPEN_BUNDLES, PENDING_ADMIN, PENDING_DEPOSITS,
+    PENDING_DEPOSIT_ID, PENDING_MINTER, ROLES,
+};
 
-use schemars::JsonSchema;
+use semver::Version;
 use serde::{Deserialize, Serialize};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:cw-bundler";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+// up to 0.1.0 the cw721-base instantiate overwrote our version with its own
+const LEGACY_CONTRACT_NAME: &str = "crates.io:cw721-base";
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct CW20Wrapper {
-    pub contract_address: Addr,
-    pub amount: Uint128,
-}
-const CW20_BUNDLE: Map<String, Vec<CW20Wrapper>> = Map::new("cw20_bundle");
-
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct CW721Wrapper {
-    pub contract_address: Addr,
-    pub token_id: String,
-}
-const CW721_BUNDLE: Map<String, Vec<CW721Wrapper>> = Map::new("cw721_bundle");
+// withdraw fees are capped at 10%
+const MAX_WITHDRAW_FEE_BPS: u16 = 1_000;
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct CW1155Wrapper {
-    pub contract_address: Addr,
-    pub token_id: String,
-    pub amount: Uint128,
-}
-const CW1155_BUNDLE: Map<String, Vec<CW1155Wrapper>> = Map::new("cw1155_bundle");
+// settings for pagination
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
 
+/// Hook payload of earlier versions, read as `DepositMsg::Deposit`
 #[derive(Serialize, Deserialize)]
 struct DepositCwMsg {
     bundle_id: String,
 }
 
+// emitted on chain as `wasm-bundle_deposit` and `wasm-bundle_withdraw`
+const DEPOSIT_EVENT: &str = "bundle_deposit";
+const WITHDRAW_EVENT: &str = "bundle_withdraw";
+
+/// An event for one asset entering or leaving a bundle, so indexers can follow its
+/// contents. Native coins carry their denom as contract, cw721 tokens an amount of 1.
+fn asset_event(
+    ty: &str,
+    bundle_id: &str,
+    asset_type: &str,
+    contract: &str,
+    token_id: &str,
+    amount: Uint128,
+) -> Event {
+    Event::new(ty)
+        .add_attribute("bundle_id", bundle_id)
+        .add_attribute("asset_type", asset_type)
+        .add_attribute("contract", contract)
+        .add_attribute("token_id", token_id)
+        .add_attribute("amount", amount)
+}
+
+/// One event per asset of `contents`
+fn contents_events(ty: &str, bundle_id: &str, contents: &BundleContentsResponse) -> Vec<Event> {
+    let cw721 = contents.cw721.iter().map(|asset| {
+        asset_event(
+            ty,
+            bundle_id,
+            "cw721",
+            asset.contract_address.as_str(),
+            &asset.token_id,
+            Uint128::new(1),
+        )
+    });
+    let cw20 = contents.cw20.iter().map(|asset| {
+        asset_event(
+            ty,
+            bundle_id,
+            "cw20",
+            asset.contract_address.as_str(),
+            "",
+            asset.amount,
+        )
+    });
+    let cw1155 = contents.cw1155.iter().map(|asset| {
+        asset_event(
+            ty,
+            bundle_id,
+            "cw1155",
+            asset.contract_address.as_str(),
+            &asset.token_id,
+            asset.amount,
+        )
+    });
+    let native = contents
+        .native
+        .iter()
+        .map(|coin| asset_event(ty, bundle_id, "native", &coin.denom, "", coin.amount));
+    cw721.chain(cw20).chain(cw1155).chain(native).collect()
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
-    deps: DepsMut,
+    mut deps: DepsMut,
     _env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> StdResult<Response> {
+    let admin = match msg.admin {
+        Some(admin) => deps.api.addr_validate(&admin)?,
+        None => info.sender.clone(),
+    };
+    let config = Config {
+        admin,
+        mint_policy: MintPolicy::Minter {},
+        fees: FeeConfig::default(),
+        paused: false,
+        max_nesting_depth: DEFAULT_MAX_NESTING_DEPTH,
+        max_bundle_assets: DEFAULT_MAX_BUNDLE_ASSETS,
+        asset_filter: AssetFilter::default(),
+    };
+    CONFIG.save(deps.storage, &config)?;
+
+    let cw721_msg = cw721_instantiate_msg {
+        name: msg.name,
+        symbol: msg.symbol,
+        minter: msg.minter,
+    };
+    let res = Cw721Contract::<Extension, Empty>::default().instantiate(
+        deps.branch(),
+        _env,
+        info,
+        cw721_msg,
+    )?;
+    // set after cw721-base, which records its own name and version
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
-    Cw721Contract::<Extension, Empty>::default().instantiate(deps, _env, info, msg)
+    Ok(res.add_attribute("action", "instantiate"))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::Mint(msg) => mint(deps, env, info, msg),
-        ExecuteMsg::Withdraw { bundle_id } => withdraw(deps, env, info, bundle_id),
-        ExecuteMsg::Receive(msg) => receive_cw20(deps, info, msg),
-        ExecuteMsg::ReceiveNft(msg) => receive_cw721(deps, info, msg),
+        ExecuteMsg::Mint(msg) => mint(deps, info, msg),
+        ExecuteMsg::CreateBundle {
+            token_uri,
+            extension,
+        } => create_bundle(deps, info, token_uri, extension),
+        ExecuteMsg::TransferNft {
+            recipient,
+            token_id,
+        } => {
+            let res = execute_cw721(
+                deps.branch(),
+                env,
+                info,
+                cw721_execute_msg::TransferNft {
+                    recipient,
+                    token_id: token_id.clone(),
+                },
+            )?;
+            clear_deposit_rights(deps.storage, &token_id)?;
+            Ok(res)
+        }
+        ExecuteMsg::SendNft {
+            contract,
+            token_id,
+            msg,
+        } => {
+            let res = execute_cw721(
+                deps.branch(),
+                env,
+                info,
+                cw721_execute_msg::SendNft {
+                    contract,
+                    token_id: token_id.clone(),
+                    msg,
+                },
+            )?;
+            clear_deposit_rights(deps.storage, &token_id)?;
+            Ok(res)
+        }
+        ExecuteMsg::Approve {
+            spender,
+            token_id,
+            expires,
+        } => execute_cw721(
+            deps,
+            env,
+            info,
+            cw721_execute_msg::Approve {
+                spender,
+                token_id,
+                expires,
+            },
+        ),
+        ExecuteMsg::Revoke { spender, token_id } => execute_cw721(
+            deps,
+            env,
+            info,
+            cw721_execute_msg::Revoke { spender, token_id },
+        ),
+        ExecuteMsg::ApproveAll { operator, expires } => execute_cw721(
+            deps,
+            env,
+            info,
+            cw721_execute_msg::ApproveAll { operator, expires },
+        ),
+        ExecuteMsg::RevokeAll { operator } => {
+            execute_cw721(deps, env, info, cw721_execute_msg::RevokeAll { operator })
+        }
+        ExecuteMsg::Withdraw {
+            bundle_id,
+            recipient,
+            use_send,
+            msg,
+            splits,
+        } => withdraw(
+            deps,
+            env,
+            info,
+            bundle_id,
+            WithdrawOptions {
+                recipient,
+                use_send,
+                msg,
+                splits,
+            },
+        ),
+        ExecuteMsg::WithdrawBatch { bundle_id, limit } => {
+            withdraw_batch(deps, env, info, bundle_id, limit)
+        }
+        ExecuteMsg::Lock { bundle_id, until } => lock(deps, env, info, bundle_id, until),
+        ExecuteMsg::Seal { bundle_id } => seal(deps, info, bundle_id),
+        ExecuteMsg::UpdateDepositors {
+            bundle_id,
+            add,
+            remove,
+        } => update_depositors(deps, info, bundle_id, add, remove),
+        ExecuteMsg::SetOpenToContributions { bundle_id, open } => {
+            set_open_to_contributions(deps, info, bundle_id, open)
+        }
+        ExecuteMsg::Unbundle {
+            bundle_id,
+            recipient,
+        } => unbundle(deps, env, info, bundle_id, recipient),
+        ExecuteMsg::WithdrawAssets {
+            bundle_id,
+            native,
+            cw20,
+            cw721,
+            cw1155,
+            discard,
+        } => withdraw_assets(
+            deps,
+            env,
+            info,
+            bundle_id,
+            BundleAssets {
+                native,
+                cw20,
+                cw721,
+                cw1155,
+            },
+            discard,
+        ),
+        ExecuteMsg::DepositNative { bundle_id } => deposit_native(deps, info, bundle_id),
+        ExecuteMsg::DepositFromApprovals {
+            bundle_id,
+            cw20,
+            cw721,
+            cw1155,
+        } => deposit_from_approvals(
+            deps,
+            env,
+            info,
+            bundle_id,
+            BundleAssets {
+                native: vec![],
+                cw20,
+                cw721,
+                cw1155,
+            },
+        ),
+        ExecuteMsg::UpdateConfig {
+            mint_policy,
+            max_nesting_depth,
+            max_bundle_assets,
+        } => update_config(
+            deps,
+            info,
+            mint_policy,
+            max_nesting_depth,
+            max_bundle_assets,
+        ),
+        ExecuteMsg::Pause {} => set_paused(deps, info, true),
+        ExecuteMsg::Unpause {} => set_paused(deps, info, false),
+        ExecuteMsg::GrantRole { role, address } => grant_role(deps, info, role, address),
+        ExecuteMsg::RevokeRole { role, address } => revoke_role(deps, info, role, address),
+        ExecuteMsg::ProposeNewAdmin { admin } => propose_new_admin(deps, info, admin),
+        ExecuteMsg::AcceptAdmin {} => accept_admin(deps, info),
+        ExecuteMsg::CancelAdminTransfer {} => cancel_admin_transfer(deps, info),
+        ExecuteMsg::ProposeNewMinter { minter } => propose_new_minter(deps, info, minter),
+        ExecuteMsg::AcceptMinter {} => accept_minter(deps, info),
+        ExecuteMsg::CancelMinterTransfer {} => cancel_minter_transfer(deps, info),
+        ExecuteMsg::UpdateAssetList {
+            filter,
+            add,
+            remove,
+        } => update_asset_list(deps, info, filter, add, remove),
+        ExecuteMsg::UpdateFees {
+            treasury,
+            mint_price,
+            withdraw_fee_bps,
+        } => update_fees(deps, info, treasury, mint_price, withdraw_fee_bps),
+        ExecuteMsg::Receive(ReceiveMsg::Cw20(msg)) => receive_cw20(deps, info, msg),
+        ExecuteMsg::Receive(ReceiveMsg::Cw1155(msg)) => receive_cw1155_single(deps, info, msg),
+        ExecuteMsg::ReceiveNft(msg) => receive_cw721(deps, env, info, msg),
         ExecuteMsg::BatchReceive(msg) => receive_cw1155(deps, info, msg),
     }
 }
 
 pub fn receive_cw20(
-    deps: DepsMut,
+    mut deps: DepsMut,
     info: MessageInfo,
     msg: Cw20ReceiveMsg,
 ) -> Result<Response, ContractError> {
-    let deposit_msg_string = msg.msg.to_base64();
-    let bytes = base64::decode(deposit_msg_string)?;
-    let deposit_msg: DepositCwMsg = serde_json_wasm::from_slice(&bytes)?;
-    let bundle_id = deposit_msg.bundle_id;
-
-    let token_info = Cw721Contract::<Extension, Empty>::default()
-        .tokens
-        .load(deps.storage, &bundle_id)?;
-    check_can_deposit(&token_info, msg.sender.clone())?;
+    check_not_paused(deps.storage)?;
+    check_asset_allowed(deps.storage, &info.sender)?;
+    let deposits = match parse_deposit_msg(&msg.msg)? {
+        DepositMsg::Split { splits } => {
+            split_deposit(deps.storage, &msg.sender, splits, msg.amount)?
+        }
+        deposit_msg => vec![(
+            open_bundle(deps.branch(), &msg.sender, deposit_msg)?,
+            msg.amount,
+        )],
+    };
 
-    let bundle = CW20_BUNDLE.may_load(deps.storage, bundle_id.clone())?;
-    if let Some(mut i) = bundle {
-        i.push(CW20Wrapper {
-            contract_address: info.sender.clone(),
-            amount: msg.amount,
-        });
-        CW20_BUNDLE.save(deps.storage, bundle_id.clone(), &i)?;
-    } else {
-        let vector = vec![CW20Wrapper {
-            contract_address: info.sender.clone(),
-            amount: msg.amount,
-        }];
-        CW20_BUNDLE.save(deps.storage, bundle_id.clone(), &vector)?;
+    let depositor = deps.api.addr_validate(&msg.sender)?;
+    let mut events = vec![];
+    for (bundle_id, amount) in deposits.iter() {
+        add_cw20(deps.storage, bundle_id, &info.sender, *amount)?;
+        check_bundle_size(deps.storage, bundle_id)?;
+        record_contribution(deps.storage, bundle_id, &depositor)?;
+        events.push(asset_event(
+            DEPOSIT_EVENT,
+            bundle_id,
+            "cw20",
+            info.sender.as_str(),
+            "",
+            *amount,
+        ));
     }
 
+    let bundle_ids: Vec<String> = deposits.into_iter().map(|(id, _)| id).collect();
     Ok(Response::default()
+        .add_events(events)
         .add_attribute("action", "deposit_cw20")
         .add_attribute("sender", msg.sender)
         .add_attribute("contract_sender", info.sender.to_string())
         .add_attribute("amount", msg.amount)
-        .add_attribute("bundle_id", bundle_id))
+        .add_attribute("bundle_id", bundle_ids.join(",")))
 }
 
 pub fn receive_cw721(
-    deps: DepsMut,
+    mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     msg: Cw721ReceiveMsg,
 ) -> Result<Response, ContractError> {
-    let deposit_msg_string = msg.msg.to_base64();
-    let bytes = base64::decode(deposit_msg_string)?;
-    let deposit_msg: DepositCwMsg = serde_json_wasm::from_slice(&bytes)?;
-    let bundle_id = deposit_msg.bundle_id;
-
-    let token_info = Cw721Contract::<Extension, Empty>::default()
-        .tokens
-        .load(deps.storage, &bundle_id)?;
-    check_can_deposit(&token_info, msg.sender.clone())?;
-
-    let bundle = CW721_BUNDLE.may_load(deps.storage, bundle_id.clone())?;
-    if let Some(mut i) = bundle {
-        i.push(CW721Wrapper {
-            contract_address: info.sender.clone(),
-            token_id: msg.token_id.clone(),
-        });
-        CW721_BUNDLE.save(deps.storage, bundle_id.clone(), &i)?;
-    } else {
-        let vector = vec![CW721Wrapper {
-            contract_address: info.sender.clone(),
-            token_id: msg.token_id.clone(),
-        }];
-        CW721_BUNDLE.save(deps.storage, bundle_id.clone(), &vector)?;
+    check_not_paused(deps.storage)?;
+    // bundles of this contract are always allowed, they cannot fake the hook
+    if info.sender != env.contract.address {
+        check_asset_allowed(deps.storage, &info.sender)?;
     }
+    let deposit_msg = parse_deposit_msg(&msg.msg)?;
+    let bundle_id = open_bundle(deps.branch(), &msg.sender, deposit_msg)?;
 
+    let asset = CW721Wrapper {
+        contract_address: info.sender.clone(),
+        token_id: msg.token_id.clone(),
+    };
+    add_cw721(deps.storage, &env.contract.address, &bundle_id, &asset)?;
+    check_bundle_size(deps.storage, &bundle_id)?;
+    let depositor = deps.api.addr_validate(&msg.sender)?;
+    record_contribution(deps.storage, &bundle_id, &depositor)?;
+
+    let event = asset_event(
+        DEPOSIT_EVENT,
+        &bundle_id,
+        "cw721",
+        info.sender.as_str(),
+        &msg.token_id,
+        Uint128::new(1),
+    );
     Ok(Response::default()
+        .add_event(event)
         .add_attribute("action", "deposit_cw721")
         .add_attribute("sender", msg.sender)
         .add_attribute("contract_sender", info.sender.to_string())
@@ -160,283 +437,4743 @@ pub fn receive_cw721(
 }
 
 pub fn receive_cw1155(
-    deps: DepsMut,
+    mut deps: DepsMut,
     info: MessageInfo,
-    mut msg: Cw1155BatchReceiveMsg,
+    msg: Cw1155BatchReceiveMsg,
 ) -> Result<Response, ContractError> {
-    let deposit_msg_string = msg.msg.to_base64();
-    let bytes = base64::decode(deposit_msg_string)?;
-    let deposit_msg: DepositCwMsg = serde_json_wasm::from_slice(&bytes)?;
-    let bundle_id = deposit_msg.bundle_id;
-
-    let token_info = Cw721Contract::<Extension, Empty>::default()
-        .tokens
-        .load(deps.storage, &bundle_id)?;
-    check_can_deposit(&token_info, msg.operator.clone())?;
-
-    let bundle = CW1155_BUNDLE.may_load(deps.storage, bundle_id.clone())?;
-    if let Some(mut i) = bundle {
-        while let Some(asset) = msg.batch.pop() {
-            i.push(CW1155Wrapper {
-                contract_address: info.sender.clone(),
-                token_id: asset.0,
-                amount: asset.1,
-            });
+    check_not_paused(deps.storage)?;
+    check_asset_allowed(deps.storage, &info.sender)?;
+    let deposits = match parse_deposit_msg(&msg.msg)? {
+        DepositMsg::Split { splits } => {
+            let (token_id, amount) = match msg.batch.as_slice() {
+                [(token_id, amount)] => (token_id.clone(), *amount),
+                _ => return Err(ContractError::InvalidSplit {}),
+            };
+            split_deposit(deps.storage, &msg.operator, splits, amount)?
+                .into_iter()
+                .map(|(bundle_id, amount)| (bundle_id, vec![(token_id.clone(), amount)]))
+                .collect()
         }
-        CW1155_BUNDLE.save(deps.storage, bundle_id.clone(), &i)?;
-    } else {
-        let mut vector = vec![];
-        while let Some(asset) = msg.batch.pop() {
-            vector.push(CW1155Wrapper {
-                contract_address: info.sender.clone(),
-                token_id: asset.0,
-                amount: asset.1,
-            });
+        deposit_msg => vec![(
+            open_bundle(deps.branch(), &msg.operator, deposit_msg)?,
+            msg.batch.clone(),
+        )],
+    };
+
+    let depositor = deps.api.addr_validate(&msg.operator)?;
+    let mut events = vec![];
+    for (bundle_id, batch) in deposits.iter() {
+        for (token_id, amount) in batch {
+            add_cw1155(deps.storage, bundle_id, &info.sender, token_id, *amount)?;
+            events.push(asset_event(
+                DEPOSIT_EVENT,
+                bundle_id,
+                "cw1155",
+                info.sender.as_str(),
+                token_id,
+                *amount,
+            ));
         }
-        CW1155_BUNDLE.save(deps.storage, bundle_id.clone(), &vector)?;
+        check_bundle_size(deps.storage, bundle_id)?;
+        record_contribution(deps.storage, bundle_id, &depositor)?;
     }
 
+    let bundle_ids: Vec<String> = deposits.into_iter().map(|(id, _)| id).collect();
     Ok(Response::default()
+        .add_events(events)
         .add_attribute("action", "deposit_cw1155")
         .add_attribute("sender", msg.operator)
         .add_attribute("contract_sender", info.sender.to_string())
-        .add_attribute("bundle_id", bundle_id))
+        .add_attribute("bundle_id", bundle_ids.join(",")))
 }
 
-pub fn mint(
-    mut deps: DepsMut,
-    env: Env,
+/// Deposit a single cw1155 token the same way as a batch of one
+pub fn receive_cw1155_single(
+    deps: DepsMut,
     info: MessageInfo,
-    msg: MintMsg,
+    msg: Cw1155ReceiveMsg,
 ) -> Result<Response, ContractError> {
-    let mint_msg = cw721_execute_msg::Mint(msg.base.clone());
-    let res =
-        Cw721Contract::<Extension, Empty>::default().execute(deps.branch(), env, info, mint_msg)?;
-    Ok(res)
+    let batch_msg = Cw1155BatchReceiveMsg {
+        operator: msg.operator,
+        from: msg.from,
+        batch: vec![(msg.token_id, msg.amount)],
+        msg: msg.msg,
+    };
+    receive_cw1155(deps, info, batch_msg)
 }
 
-pub fn check_can_deposit(
-    token: &TokenInfo<Extension>,
-    sender: String,
-) -> Result<(), ContractError> {
-    // only owner can deposit
-    if token.owner == sender {
-        return Ok(());
+/// Parse a hook payload, also accepting the `{"bundle_id": ..}` payload of earlier versions
+fn parse_deposit_msg(msg: &Binary) -> Result<DepositMsg, ContractError> {
+    let bytes = base64::decode(msg.to_base64())?;
+    match serde_json_wasm::from_slice::<DepositMsg>(&bytes) {
+        Ok(deposit_msg) => Ok(deposit_msg),
+        Err(_) => {
+            let deposit_msg: DepositCwMsg = serde_json_wasm::from_slice(&bytes)?;
+            Ok(DepositMsg::Deposit {
+                bundle_id: deposit_msg.bundle_id,
+            })
+        }
     }
-    Err(ContractError::Unauthorized {})
 }
 
-pub fn check_can_withdraw(
-    info: &MessageInfo,
-    token: &TokenInfo<Extension>,
-) -> Result<(), ContractError> {
-    // only owner can withdraw
-    if token.owner == info.sender {
-        return Ok(());
+/// Return the bundle a deposit goes to, minting it first for `CreateBundle`
+fn open_bundle(
+    deps: DepsMut,
+    depositor: &str,
+    deposit_msg: DepositMsg,
+) -> Result<String, ContractError> {
+    match deposit_msg {
+        DepositMsg::Deposit { bundle_id } => {
+            let token_info = Cw721Contract::<Extension, Empty>::default()
+                .tokens
+                .load(deps.storage, &bundle_id)?;
+            check_can_deposit(deps.storage, &bundle_id, &token_info, depositor)?;
+            check_not_sealed(deps.storage, &bundle_id)?;
+            Ok(bundle_id)
+        }
+        DepositMsg::CreateBundle {
+            bundle_id,
+            owner,
+            token_uri,
+            extension,
+        } => {
+            let depositor = deps.api.addr_validate(depositor)?;
+            let config = CONFIG.load(deps.storage)?;
+            // hook senders cannot be authenticated, so minter privileges never apply here
+            // and no mint price can be charged to them
+            if config.fees.mint_price.is_some() {
+                return Err(ContractError::MintPriceOnDeposit {});
+            }
+            // any contract can fake a hook, so a per owner cap only holds when the
+            // calling asset contracts are trusted
+            let capped = matches!(
+                config.mint_policy,
+                MintPolicy::Public {
+                    max_per_owner: Some(_)
+                }
+            );
+            if capped && config.asset_filter != (AssetFilter::Allowlist {}) {
+                return Err(ContractError::CappedDepositNotAllowlisted {});
+            }
+
+            // the cap applies to whoever ends up holding the bundle, not the sender
+            let owner = match owner {
+                Some(owner) => deps.api.addr_validate(&owner)?,
+                None => depositor,
+            };
+            count_created_bundle(deps.storage, &config, &owner, &bundle_id)?;
+            create_token(deps.storage, &bundle_id, owner, token_uri, extension)?;
+            Ok(bundle_id)
+        }
+        DepositMsg::Split { .. } => Err(ContractError::InvalidSplit {}),
     }
-    Err(ContractError::Unauthorized {})
 }
 
-pub fn withdraw(
+/// Check a split of `amount` over bundles the depositor may deposit into
+fn split_deposit(
+    storage: &dyn Storage,
+    depositor: &str,
+    splits: Vec<BundleSplit>,
+    amount: Uint128,
+) -> Result<Vec<(String, Uint128)>, ContractError> {
+    let mut total = Uint128::zero();
+    for split in splits.iter() {
+        if split.amount.is_zero() {
+            return Err(ContractError::InvalidSplit {});
+        }
+        total = total.checked_add(split.amount).map_err(StdError::from)?;
+        let token_info = Cw721Contract::<Extension, Empty>::default()
+            .tokens
+            .load(storage, &split.bundle_id)?;
+        check_can_deposit(storage, &split.bundle_id, &token_info, depositor)?;
+        check_not_sealed(storage, &split.bundle_id)?;
+    }
+    if total != amount {
+        return Err(ContractError::InvalidSplit {});
+    }
+    Ok(splits
+        .into_iter()
+        .map(|split| (split.bundle_id, split.amount))
+        .collect())
+}
+
+pub fn deposit_native(
+    deps: DepsMut,
+    info: MessageInfo,
+    bundle_id: String,
+) -> Result<Response, ContractError> {
+    check_not_paused(deps.storage)?;
+    let funds: Vec<Coin> = info
+        .funds
+        .iter()
+        .filter(|coin| !coin.amount.is_zero())
+        .cloned()
+        .collect();
+    if funds.is_empty() {
+        return Err(ContractError::NoFunds {});
+    }
+
+    let token_info = Cw721Contract::<Extension, Empty>::default()
+        .tokens
+        .load(deps.storage, &bundle_id)?;
+    check_can_deposit(deps.storage, &bundle_id, &token_info, info.sender.as_str())?;
+    check_not_sealed(deps.storage, &bundle_id)?;
+
+    for coin in funds.iter() {
+        add_native(deps.storage, &bundle_id, coin)?;
+    }
+    check_bundle_size(deps.storage, &bundle_id)?;
+    record_contribution(deps.storage, &bundle_id, &info.sender)?;
+
+    let events = funds.iter().map(|coin| {
+        asset_event(
+            DEPOSIT_EVENT,
+            &bundle_id,
+            "native",
+            &coin.denom,
+            "",
+            coin.amount,
+        )
+    });
+    let amount: Vec<String> = funds.iter().map(|coin| coin.to_string()).collect();
+    Ok(Response::default()
+        .add_events(events)
+        .add_attribute("action", "deposit_native")
+        .add_attribute("sender", info.sender.to_string())
+        .add_attribute("amount", amount.join(","))
+        .add_attribute("bundle_id", bundle_id))
+}
+
+pub fn deposit_from_approvals(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     bundle_id: String,
+    assets: BundleAssets,
 ) -> Result<Response, ContractError> {
+    check_not_paused(deps.storage)?;
+    if assets.cw20.is_empty() && assets.cw721.is_empty() && assets.cw1155.is_empty() {
+        return Err(ContractError::NoFunds {});
+    }
+
     let token_info = Cw721Contract::<Extension, Empty>::default()
         .tokens
         .load(deps.storage, &bundle_id)?;
-    check_can_withdraw(&info, &token_info)?;
+    check_can_deposit(deps.storage, &bundle_id, &token_info, info.sender.as_str())?;
+    check_not_sealed(deps.storage, &bundle_id)?;
 
-    let mut cw_transfer_cosmos_msgs = vec![];
-    let bundle = CW721_BUNDLE.may_load(deps.storage, bundle_id.clone())?;
-    if let Some(mut i) = bundle {
-        while let Some(asset) = i.pop() {
-            let transfer_cw721_msg = Cw721ExecuteMsg::TransferNft {
-                recipient: info.sender.to_string(),
-                token_id: asset.token_id,
-            };
-            let exec_cw721_transfer = WasmMsg::Execute {
-                contract_addr: asset.contract_address.to_string(),
-                msg: to_binary(&transfer_cw721_msg)?,
-                funds: vec![],
-            };
-            let cw721_transfer_cosmos_msg: CosmosMsg = exec_cw721_transfer.into();
-            cw_transfer_cosmos_msgs.push(cw721_transfer_cosmos_msg);
+    // every transfer replies on success, only then is its asset recorded
+    let mut pending = vec![];
+
+    for asset in assets.cw721 {
+        let contract_address = deps.api.addr_validate(&asset.contract_address)?;
+        if contract_address != env.contract.address {
+            check_asset_allowed(deps.storage, &contract_address)?;
         }
-        CW721_BUNDLE.save(deps.storage, bundle_id.clone(), &i)?;
+        check_token_owner(
+            deps.as_ref(),
+            &env,
+            &info.sender,
+            &contract_address,
+            &asset.token_id,
+        )?;
+        let transfer_cw721_msg = Cw721ExecuteMsg::TransferNft {
+            recipient: env.contract.address.to_string(),
+            token_id: asset.token_id.clone(),
+        };
+        let exec_cw721_transfer = WasmMsg::Execute {
+            contract_addr: contract_address.to_string(),
+            msg: to_binary(&transfer_cw721_msg)?,
+            funds: vec![],
+        };
+        let asset = PendingAsset::Cw721(CW721Wrapper {
+            contract_address,
+            token_id: asset.token_id,
+        });
+        pending.push((exec_cw721_transfer, asset));
     }
 
-    let bundle = CW20_BUNDLE.may_load(deps.storage, bundle_id.clone())?;
-    if let Some(mut i) = bundle {
-        while let Some(asset) = i.pop() {
-            let transfer_cw20_msg = Cw20ExecuteMsg::Transfer {
-                recipient: info.sender.to_string(),
-                amount: asset.amount,
-            };
-            let exec_cw20_transfer = WasmMsg::Execute {
-                contract_addr: asset.contract_address.to_string(),
-                msg: to_binary(&transfer_cw20_msg)?,
-                funds: vec![],
-            };
-            let cw20_transfer_cosmos_msg: CosmosMsg = exec_cw20_transfer.into();
-            cw_transfer_cosmos_msgs.push(cw20_transfer_cosmos_msg);
-        }
-        CW20_BUNDLE.save(deps.storage, bundle_id.clone(), &i)?;
+    for asset in assets.cw20 {
+        let contract_address = deps.api.addr_validate(&asset.contract_address)?;
+        check_asset_allowed(deps.storage, &contract_address)?;
+        let transfer_cw20_msg = Cw20ExecuteMsg::TransferFrom {
+            owner: info.sender.to_string(),
+            recipient: env.contract.address.to_string(),
+            amount: asset.amount,
+        };
+        let exec_cw20_transfer = WasmMsg::Execute {
+            contract_addr: contract_address.to_string(),
+            msg: to_binary(&transfer_cw20_msg)?,
+            funds: vec![],
+        };
+        let asset = PendingAsset::Cw20(CW20Wrapper {
+            contract_address,
+            amount: asset.amount,
+        });
+        pending.push((exec_cw20_transfer, asset));
     }
 
-    let bundle = CW1155_BUNDLE.may_load(deps.storage, bundle_id.clone())?;
-    let mut cw1155_batch = vec![];
-    if let Some(mut i) = bundle {
-        while let Some(asset) = i.pop() {
-            cw1155_batch.push((asset.token_id, asset.amount));
-        }
-        CW1155_BUNDLE.save(deps.storage, bundle_id, &i)?;
-        let transfer_cw1155_msg = Cw1155ExecuteMsg::BatchSendFrom {
-            from: env.contract.address.to_string(),
-            to: info.sender.to_string(),
-            batch: cw1155_batch,
+    for asset in assets.cw1155 {
+        let contract_address = deps.api.addr_validate(&asset.contract_address)?;
+        check_asset_allowed(deps.storage, &contract_address)?;
+        let transfer_cw1155_msg = Cw1155ExecuteMsg::SendFrom {
+            from: info.sender.to_string(),
+            to: env.contract.address.to_string(),
+            token_id: asset.token_id.clone(),
+            value: asset.amount,
+            // no receive hook, the asset is recorded on reply instead
             msg: None,
         };
         let exec_cw1155_transfer = WasmMsg::Execute {
-            contract_addr: env.contract.address.to_string(),
+            contract_addr: contract_address.to_string(),
             msg: to_binary(&transfer_cw1155_msg)?,
             funds: vec![],
         };
-        let cw1155_transfer_cosmos_msg: CosmosMsg = exec_cw1155_transfer.into();
-        cw_transfer_cosmos_msgs.push(cw1155_transfer_cosmos_msg);
+        let asset = PendingAsset::Cw1155(CW1155Wrapper {
+            contract_address,
+            token_id: asset.token_id,
+            amount: asset.amount,
+        });
+        pending.push((exec_cw1155_transfer, asset));
     }
 
-    Ok(Response::new()
-        .add_messages(cw_transfer_cosmos_msgs)
-        .add_attribute("method", "withdraw"))
+    let mut id = PENDING_DEPOSIT_ID
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    let mut sub_msgs = vec![];
+    for (msg, asset) in pending {
+        id += 1;
+        let deposit = PendingDeposit {
+            bundle_id: bundle_id.clone(),
+            depositor: info.sender.clone(),
+            asset,
+        };
+        PENDING_DEPOSITS.save(deps.storage, U64Key::new(id), &deposit)?;
+        sub_msgs.push(SubMsg::reply_on_success(msg, id));
+    }
+    PENDING_DEPOSIT_ID.save(deps.storage, &id)?;
+
+    Ok(Response::default()
+        .add_submessages(sub_msgs)
+        .add_attribute("action", "deposit_from_approvals")
+        .add_attribute("sender", info.sender.to_string())
+        .add_attribute("bundle_id", bundle_id))
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        // add custom queries here
-        _ => {
-            let msg: cw721_query_msg = msg.into();
-            let response = Cw721Contract::<Extension, Empty>::default().query(deps, _env, msg)?;
-            Ok(response)
-        }
+/// Check `owner` holds a cw721 token it wants to pull in. This contract holds every
+/// bundled token, so those can never be pulled into another bundle.
+fn check_token_owner(
+    deps: Deps,
+    env: &Env,
+    owner: &Addr,
+    contract_address: &Addr,
+    token_id: &str,
+) -> Result<(), ContractError> {
+    check_not_bundled(deps.storage, contract_address, token_id)?;
+    let token_owner = if *contract_address == env.contract.address {
+        Cw721Contract::<Extension, Empty>::default()
+            .tokens
+            .load(deps.storage, token_id)?
+            .owner
+    } else {
+        let res: OwnerOfResponse = deps.querier.query_wasm_smart(
+            contract_address,
+            &Cw721QueryMsg::OwnerOf {
+                token_id: token_id.to_string(),
+                include_expired: None,
+            },
+        )?;
+        deps.api.addr_validate(&res.owner)?
+    };
+    if token_owner == env.contract.address || token_owner != *owner {
+        return Err(ContractError::Unauthorized {});
     }
+    Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use cosmwasm_std::from_binary;
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cw721::NumTokensResponse;
-    use cw721_base::msg::MintMsg as Cw721MintMsg;
-    use cw721_base::{Cw721Contract, Extension};
+/// Mint a bundle as the cw721 minter or a holder of the minter role
+pub fn mint(deps: DepsMut, info: MessageInfo, msg: MintMsg) -> Result<Response, ContractError> {
+    check_not_paused(deps.storage)?;
+    let minter = Cw721Contract::<Extension, Empty>::default()
+        .minter
+        .load(deps.storage)?;
+    if info.sender != minter && !has_role(deps.storage, Role::Minter, &info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    let fees = CONFIG.load(deps.storage)?.fees;
+    let mint_fee = collect_mint_fee(&fees, &info.sender, &info.funds)?;
 
-    const TOKEN_ID: &str = "a";
-    const MINTER: &str = "minter_address";
-    const ALICE: &str = "alice_address";
-    const CONTRACT: &str = "contract_address";
+    let owner = deps.api.addr_validate(&msg.base.owner)?;
+    create_token(
+        deps.storage,
+        &msg.base.token_id,
+        owner,
+        msg.base.token_uri,
+        msg.base.extension,
+    )?;
 
-    fn setup_contract(deps: DepsMut) {
-        let msg = InstantiateMsg {
-            name: "CW Bundled Asset".into(),
-            symbol: "CWBUNDLE".into(),
-            minter: MINTER.into(),
-        };
-        let info = mock_info(MINTER, &[]);
-        let res = instantiate(deps, mock_env(), info, msg).unwrap();
-        assert_eq!(0, res.messages.len());
-    }
+    let res = Response::new()
+        .add_attribute("action", "mint")
+        .add_attribute("minter", info.sender)
+        .add_attribute("token_id", msg.base.token_id);
+    Ok(mint_fee.add_to(res))
+}
 
-    #[test]
-    fn proper_instantiation() {
-        let mut deps = mock_dependencies(&[]);
-        setup_contract(deps.as_mut());
-    }
+/// Mint a bundle to the sender under the next free numeric id, if public minting is on
+pub fn create_bundle(
+    deps: DepsMut,
+    info: MessageInfo,
+    token_uri: Option<String>,
+    extension: Extension,
+) -> Result<Response, ContractError> {
+    check_not_paused(deps.storage)?;
+    let config = CONFIG.load(deps.storage)?;
 
-    #[test]
-    fn mint() {
-        let mut deps = mock_dependencies(&[]);
-        setup_contract(deps.as_mut());
+    // skip ids the minter has already taken
+    let tokens = Cw721Contract::<Extension, Empty>::default().tokens;
+    let mut count = BUNDLE_COUNT.may_load(deps.storage)?.unwrap_or_default();
+    let bundle_id = loop {
+        count += 1;
+        let bundle_id = count.to_string();
+        if tokens.may_load(deps.storage, &bundle_id)?.is_none() {
+            break bundle_id;
+        }
+    };
+    count_created_bundle(deps.storage, &config, &info.sender, &bundle_id)?;
+    BUNDLE_COUNT.save(deps.storage, &count)?;
 
-        let info = mock_info(MINTER, &[]);
+    let mint_fee = collect_mint_fee(&config.fees, &info.sender, &info.funds)?;
+    create_token(
+        deps.storage,
+        &bundle_id,
+        info.sender.clone(),
+        token_uri,
+        extension,
+    )?;
 
-        let mint_msg = cw721_execute_msg::Mint(Cw721MintMsg {
-            token_id: TOKEN_ID.into(),
-            owner: ALICE.into(),
-            extension: None,
-            token_uri: Some("ipfs://QmVKZ5YZYDqdnAQo93kaYbcMtzGgx9kvpAVwoERm5mZezh".to_string()),
-        });
-        let _ = Cw721Contract::<Extension, Empty>::default().execute(
-            deps.as_mut(),
-            mock_env(),
-            info,
-            mint_msg,
-        );
+    let res = Response::default()
+        .add_attribute("action", "create_bundle")
+        .add_attribute("owner", info.sender.to_string())
+        .add_attribute("bundle_id", bundle_id);
+    Ok(mint_fee.add_to(res))
+}
 
-        // ensure num tokens increases
-        let count = Cw721Contract::<Extension, Empty>::default()
-            .query(deps.as_ref(), mock_env(), cw721_query_msg::NumTokens {})
-            .unwrap();
-        let response: NumTokensResponse = from_binary(&count).unwrap();
-        assert_eq!(1, response.count);
+/// Count a bundle `creator` mints for itself outside of `Mint`, which needs public minting
+/// and stays within the per owner cap
+fn count_created_bundle(
+    storage: &mut dyn Storage,
+    config: &Config,
+    creator: &Addr,
+    bundle_id: &str,
+) -> Result<(), ContractError> {
+    let max_per_owner = match config.mint_policy {
+        MintPolicy::Public { max_per_owner } => max_per_owner,
+        MintPolicy::Minter {} => return Err(ContractError::PublicMintDisabled {}),
+    };
+    let created = CREATED_BUNDLES
+        .may_load(storage, creator)?
+        .unwrap_or_default();
+    if let Some(max) = max_per_owner {
+        if created >= max {
+            return Err(ContractError::MintLimitReached { max });
+        }
     }
+    CREATED_BUNDLES.save(storage, creator, &(created + 1))?;
+    BUNDLE_CREATORS.save(storage, bundle_id, creator)?;
+    Ok(())
+}
 
-    #[test]
-    fn try_receive_cw20() {
-        let mut deps = mock_dependencies(&[]);
-        setup_contract(deps.as_mut());
+/// Give the creator of an unbundled bundle its place under the cap back
+fn uncount_created_bundle(storage: &mut dyn Storage, bundle_id: &str) -> StdResult<()> {
+    let creator = match BUNDLE_CREATORS.may_load(storage, bundle_id)? {
+        Some(creator) => creator,
+        None => return Ok(()),
+    };
+    BUNDLE_CREATORS.remove(storage, bundle_id);
+    let created = CREATED_BUNDLES
+        .may_load(storage, &creator)?
+        .unwrap_or_default();
+    if created <= 1 {
+        CREATED_BUNDLES.remove(storage, &creator);
+    } else {
+        CREATED_BUNDLES.save(storage, &creator, &(created - 1))?;
+    }
+    Ok(())
+}
 
-        let info = mock_info(MINTER, &[]);
-        let mint_msg = cw721_execute_msg::Mint(Cw721MintMsg {
-            token_id: TOKEN_ID.into(),
-            owner: ALICE.into(),
-            extension: None,
-            token_uri: Some("ipfs://QmVKZ5YZYDqdnAQo93kaYbcMtzGgx9kvpAVwoERm5mZezh".to_string()),
-        });
-        let _ = Cw721Contract::<Extension, Empty>::default().execute(
-            deps.as_mut(),
-            mock_env(),
-            info,
-            mint_msg,
-        );
+/// Messages paying fees to the treasury, with attributes itemizing them
+#[derive(Default)]
+struct FeePayment {
+    msgs: Vec<CosmosMsg>,
+    attributes: Vec<Attribute>,
+}
 
-        let info = mock_info(CONTRACT, &[]);
-        let msg = Cw20ReceiveMsg {
-            sender: ALICE.into(),
-            amount: Uint128::from(2u128),
-            msg: Binary::from_base64(&"eyJidW5kbGVfaWQiOiAiYSJ9".to_string()).unwrap(),
-        };
+impl FeePayment {
+    fn add_to(self, res: Response) -> Response {
+        res.add_messages(self.msgs).add_attributes(self.attributes)
+    }
+}
 
-        // receive cw20 tokens
-        let res = receive_cw20(deps.as_mut(), info, msg).unwrap();
-        let info = mock_info(CONTRACT, &[]);
-        let msg = Cw20ReceiveMsg {
-            sender: ALICE.into(),
-            amount: Uint128::from(2u128),
-            msg: Binary::from_base64(&"eyJidW5kbGVfaWQiOiAiYSJ9".to_string()).unwrap(),
-        };
-        assert_eq!(
-            res,
-            Response::default()
-                .add_attribute("action", "deposit_cw20")
-                .add_attribute("sender", msg.sender)
-                .add_attribute("contract_sender", info.sender.to_string())
-                .add_attribute("amount", "2")
-                .add_attribute("bundle_id", "a")
-        );
+/// Charge the mint price to `payer`, a native price has to be attached exactly and no
+/// funds are taken otherwise
+fn collect_mint_fee(
+    fees: &FeeConfig,
+    payer: &Addr,
+    funds: &[Coin],
+) -> Result<FeePayment, ContractError> {
+    let paid: Vec<&Coin> = funds.iter().filter(|c| !c.amount.is_zero()).collect();
+    let (price, treasury) = match (&fees.mint_price, &fees.treasury) {
+        (Some(price), Some(treasury)) => (price, treasury),
+        _ if !paid.is_empty() => return Err(ContractError::UnexpectedFunds {}),
+        _ => return Ok(FeePayment::default()),
+    };
 
-        // ensure num tokens in bundle is 1
-        let bundle = CW20_BUNDLE
-            .may_load(&deps.storage, "a".into())
-            .unwrap()
-            .unwrap();
-        assert_eq!(1, bundle.len());
+    let (msg, fee): (CosmosMsg, String) = match price {
+        MintPrice::Native(coin) => {
+            if paid != vec![coin] {
+                return Err(ContractError::MintFeeRequired {
+                    fee: coin.to_string(),
+                });
+            }
+            let native_transfer_cosmos_msg = BankMsg::Send {
+                to_address: treasury.to_string(),
+                amount: vec![coin.clone()],
+            };
+            (native_transfer_cosmos_msg.into(), coin.to_string())
+        }
+        MintPrice::Cw20(_) if !paid.is_empty() => return Err(ContractError::UnexpectedFunds {}),
+        MintPrice::Cw20(asset) => {
+            let transfer_cw20_msg = Cw20ExecuteMsg::TransferFrom {
+                owner: payer.to_string(),
+                recipient: treasury.to_string(),
+                amount: asset.amount,
+            };
+            let exec_cw20_transfer = WasmMsg::Execute {
+                contract_addr: asset.contract_address.to_string(),
+                msg: to_binary(&transfer_cw20_msg)?,
+                funds: vec![],
+            };
+            let fee = format!("{}{}", asset.amount, asset.contract_address);
+            (exec_cw20_transfer.into(), fee)
+        }
+    };
+    Ok(FeePayment {
+        msgs: vec![msg],
+        attributes: vec![attr("mint_fee", fee)],
+    })
+}
+
+/// Splits the withdraw fee off native and cw20 amounts leaving a bundle
+struct WithdrawFee {
+    treasury: Option<Addr>,
+    bps: u16,
+    native: Vec<Coin>,
+    payment: FeePayment,
+}
+
+impl WithdrawFee {
+    fn load(storage: &dyn Storage) -> StdResult<Self> {
+        let fees = CONFIG.load(storage)?.fees;
+        Ok(WithdrawFee {
+            treasury: fees.treasury,
+            bps: fees.withdraw_fee_bps,
+            native: vec![],
+            payment: FeePayment::default(),
+        })
+    }
+
+    fn fee_on(&self, amount: Uint128) -> Uint128 {
+        match self.treasury {
+            Some(_) => amount.multiply_ratio(self.bps, 10_000u128),
+            None => Uint128::zero(),
+        }
+    }
+
+    /// Returns the coins left for the recipient
+    fn take_native(&mut self, coins: Vec<Coin>) -> Vec<Coin> {
+        let mut left = vec![];
+        for coin in coins {
+            let fee = self.fee_on(coin.amount);
+            if !fee.is_zero() {
+                let fee = Coin::new(fee.u128(), coin.denom.clone());
+                self.payment
+                    .attributes
+                    .push(attr("withdraw_fee", fee.to_string()));
+                self.native.push(fee);
+            }
+            let amount = coin.amount - fee;
+            if !amount.is_zero() {
+                left.push(Coin::new(amount.u128(), coin.denom));
+            }
+        }
+        left
+    }
+
+    /// Returns the amount left for the recipient
+    fn take_cw20(&mut self, contract_address: &Addr, amount: Uint128) -> StdResult<Uint128> {
+        let fee = self.fee_on(amount);
+        if let Some(treasury) = self.treasury.as_ref().filter(|_| !fee.is_zero()) {
+            let transfer_cw20_msg = Cw20ExecuteMsg::Transfer {
+                recipient: treasury.to_string(),
+                amount: fee,
+            };
+            let exec_cw20_transfer = WasmMsg::Execute {
+                contract_addr: contract_address.to_string(),
+                msg: to_binary(&transfer_cw20_msg)?,
+                funds: vec![],
+            };
+            self.payment.msgs.push(exec_cw20_transfer.into());
+            self.payment
+                .attributes
+                .push(attr("withdraw_fee", format!("{}{}", fee, contract_address)));
+        }
+        Ok(amount - fee)
+    }
+
+    /// Returns the amount left for the recipient
+    fn take_cw1155(
+        &mut self,
+        env: &Env,
+        contract_address: &Addr,
+        token_id: &str,
+        amount: Uint128,
+    ) -> StdResult<Uint128> {
+        let fee = self.fee_on(amount);
+        if let Some(treasury) = self.treasury.as_ref().filter(|_| !fee.is_zero()) {
+            let transfer_cw1155_msg = Cw1155ExecuteMsg::SendFrom {
+                from: env.contract.address.to_string(),
+                to: treasury.to_string(),
+                token_id: token_id.to_string(),
+                value: fee,
+                msg: None,
+            };
+            let exec_cw1155_transfer = WasmMsg::Execute {
+                contract_addr: contract_address.to_string(),
+                msg: to_binary(&transfer_cw1155_msg)?,
+                funds: vec![],
+            };
+            self.payment.msgs.push(exec_cw1155_transfer.into());
+            self.payment.attributes.push(attr(
+                "withdraw_fee",
+                format!("{}{}/{}", fee, contract_address, token_id),
+            ));
+        }
+        Ok(amount - fee)
+    }
+
+    fn finish(mut self) -> FeePayment {
+        if let Some(treasury) = self.treasury.as_ref().filter(|_| !self.native.is_empty()) {
+            let native_transfer_cosmos_msg = BankMsg::Send {
+                to_address: treasury.to_string(),
+                amount: self.native,
+            };
+            self.payment.msgs.push(native_transfer_cosmos_msg.into());
+        }
+        self.payment
+    }
+}
+
+/// Same as a cw721-base mint, without requiring the minter
+fn create_token(
+    storage: &mut dyn Storage,
+    bundle_id: &str,
+    owner: Addr,
+    token_uri: Option<String>,
+    extension: Extension,
+) -> Result<(), ContractError> {
+    let cw721_contract = Cw721Contract::<Extension, Empty>::default();
+    let token = TokenInfo {
+        owner,
+        approvals: vec![],
+        token_uri,
+        extension,
+    };
+    cw721_contract
+        .tokens
+        .update(storage, bundle_id, |old| match old {
+            Some(_) => Err(ContractError::Claimed {}),
+            None => Ok(token),
+        })?;
+    cw721_contract.increment_tokens(storage)?;
+    Ok(())
+}
+
+/// Forward a standard cw721 message for the bundle tokens to `cw721_base`
+pub fn execute_cw721(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: cw721_execute_msg<Extension>,
+) -> Result<Response, ContractError> {
+    let res = Cw721Contract::<Extension, Empty>::default().execute(deps, env, info, msg)?;
+    Ok(res)
+}
+
+pub fn update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    mint_policy: Option<MintPolicy>,
+    max_nesting_depth: Option<u32>,
+    max_bundle_assets: Option<u32>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if let Some(mint_policy) = mint_policy {
+        config.mint_policy = mint_policy;
+    }
+    // a limit of 0 would silently refuse every new asset or nested bundle
+    if let Some(max_nesting_depth) = max_nesting_depth {
+        if max_nesting_depth == 0 {
+            return Err(ContractError::InvalidConfig {
+                reason: "max_nesting_depth must be at least 1".to_string(),
+            });
+        }
+        config.max_nesting_depth = max_nesting_depth;
+    }
+    if let Some(max_bundle_assets) = max_bundle_assets {
+        if max_bundle_assets == 0 {
+            return Err(ContractError::InvalidConfig {
+                reason: "max_bundle_assets must be at least 1".to_string(),
+            });
+        }
+        config.max_bundle_assets = max_bundle_assets;
+    }
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::default()
+        .add_attribute("action", "update_config")
+        .add_attribute("sender", info.sender.to_string()))
+}
+
+/// Refuse or accept deposits and mints again. Withdrawals are never paused, so owners
+/// can always get their assets out.
+pub fn set_paused(
+    deps: DepsMut,
+    info: MessageInfo,
+    paused: bool,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    check_role(deps.storage, &config, Role::Pauser, &info.sender)?;
+
+    config.paused = paused;
+    CONFIG.save(deps.storage, &config)?;
+
+    let action = if paused { "pause" } else { "unpause" };
+    Ok(Response::default()
+        .add_attribute("action", action)
+        .add_attribute("sender", info.sender.to_string())
+        .add_event(Event::new(action).add_attribute("paused", paused.to_string())))
+}
+
+pub fn grant_role(
+    deps: DepsMut,
+    info: MessageInfo,
+    role: Role,
+    address: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let address = deps.api.addr_validate(&address)?;
+    ROLES.save(deps.storage, (role.as_str(), &address), &Empty {})?;
+
+    Ok(Response::default()
+        .add_attribute("action", "grant_role")
+        .add_attribute("sender", info.sender.to_string())
+        .add_attribute("role", role.as_str())
+        .add_attribute("address", address))
+}
+
+pub fn revoke_role(
+    deps: DepsMut,
+    info: MessageInfo,
+    role: Role,
+    address: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let address = deps.api.addr_validate(&address)?;
+    ROLES.remove(deps.storage, (role.as_str(), &address));
+
+    Ok(Response::default()
+        .add_attribute("action", "revoke_role")
+        .add_attribute("sender", info.sender.to_string())
+        .add_attribute("role", role.as_str())
+        .add_attribute("address", address))
+}
+
+fn has_role(storage: &dyn Storage, role: Role, address: &Addr) -> bool {
+    ROLES.has(storage, (role.as_str(), address))
+}
+
+/// The admin or a holder of `role`
+fn check_role(
+    storage: &dyn Storage,
+    config: &Config,
+    role: Role,
+    sender: &Addr,
+) -> Result<(), ContractError> {
+    if *sender != config.admin && !has_role(storage, role, sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+pub fn propose_new_admin(
+    deps: DepsMut,
+    info: MessageInfo,
+    admin: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let admin = deps.api.addr_validate(&admin)?;
+    PENDING_ADMIN.save(deps.storage, &admin)?;
+
+    Ok(Response::default()
+        .add_attribute("action", "propose_new_admin")
+        .add_attribute("sender", info.sender.to_string())
+        .add_attribute("admin", admin))
+}
+
+pub fn accept_admin(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let pending = PENDING_ADMIN
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoPendingTransfer {})?;
+    if info.sender != pending {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut config = CONFIG.load(deps.storage)?;
+    config.admin = pending;
+    CONFIG.save(deps.storage, &config)?;
+    PENDING_ADMIN.remove(deps.storage);
+
+    Ok(Response::default()
+        .add_attribute("action", "accept_admin")
+        .add_attribute("sender", info.sender.to_string()))
+}
+
+pub fn cancel_admin_transfer(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    if PENDING_ADMIN.may_load(deps.storage)?.is_none() {
+        return Err(ContractError::NoPendingTransfer {});
+    }
+    PENDING_ADMIN.remove(deps.storage);
+
+    Ok(Response::default()
+        .add_attribute("action", "cancel_admin_transfer")
+        .add_attribute("sender", info.sender.to_string()))
+}
+
+/// The minter or the admin, so a leaked minter key can be rotated out
+fn check_can_transfer_minter(storage: &dyn Storage, sender: &Addr) -> Result<(), ContractError> {
+    let minter = Cw721Contract::<Extension, Empty>::default()
+        .minter
+        .load(storage)?;
+    if *sender != minter && *sender != CONFIG.load(storage)?.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+pub fn propose_new_minter(
+    deps: DepsMut,
+    info: MessageInfo,
+    minter: String,
+) -> Result<Response, ContractError> {
+    check_can_transfer_minter(deps.storage, &info.sender)?;
+
+    let minter = deps.api.addr_validate(&minter)?;
+    PENDING_MINTER.save(deps.storage, &minter)?;
+
+    Ok(Response::default()
+        .add_attribute("action", "propose_new_minter")
+        .add_attribute("sender", info.sender.to_string())
+        .add_attribute("minter", minter))
+}
+
+pub fn accept_minter(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let pending = PENDING_MINTER
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoPendingTransfer {})?;
+    if info.sender != pending {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    Cw721Contract::<Extension, Empty>::default()
+        .minter
+        .save(deps.storage, &pending)?;
+    PENDING_MINTER.remove(deps.storage);
+
+    Ok(Response::default()
+        .add_attribute("action", "accept_minter")
+        .add_attribute("sender", info.sender.to_string()))
+}
+
+pub fn cancel_minter_transfer(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    check_can_transfer_minter(deps.storage, &info.sender)?;
+    if PENDING_MINTER.may_load(deps.storage)?.is_none() {
+        return Err(ContractError::NoPendingTransfer {});
+    }
+    PENDING_MINTER.remove(deps.storage);
+
+    Ok(Response::default()
+        .add_attribute("action", "cancel_minter_transfer")
+        .add_attribute("sender", info.sender.to_string()))
+}
+
+pub fn update_fees(
+    deps: DepsMut,
+    info: MessageInfo,
+    treasury: Option<String>,
+    mint_price: Option<Price>,
+    withdraw_fee_bps: u16,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    check_role(deps.storage, &config, Role::FeeManager, &info.sender)?;
+
+    let treasury = treasury
+        .map(|treasury| deps.api.addr_validate(&treasury))
+        .transpose()?;
+    let mint_price = match mint_price {
+        Some(Price::Native(coin)) => Some(MintPrice::Native(coin)),
+        Some(Price::Cw20(asset)) => Some(MintPrice::Cw20(CW20Wrapper {
+            contract_address: deps.api.addr_validate(&asset.contract_address)?,
+            amount: asset.amount,
+        })),
+        None => None,
+    };
+    if withdraw_fee_bps > MAX_WITHDRAW_FEE_BPS {
+        return Err(ContractError::InvalidFees {
+            reason: format!("withdraw fee above {} basis points", MAX_WITHDRAW_FEE_BPS),
+        });
+    }
+    if treasury.is_none() && (mint_price.is_some() || withdraw_fee_bps > 0) {
+        return Err(ContractError::InvalidFees {
+            reason: "no treasury to pay fees to".into(),
+        });
+    }
+    config.fees = FeeConfig {
+        treasury,
+        mint_price,
+        withdraw_fee_bps,
+    };
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::default()
+        .add_attribute("action", "update_fees")
+        .add_attribute("sender", info.sender.to_string()))
+}
+
+pub fn update_asset_list(
+    deps: DepsMut,
+    info: MessageInfo,
+    filter: Option<AssetFilter>,
+    add: Vec<String>,
+    remove: Vec<String>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    check_role(deps.storage, &config, Role::AllowlistManager, &info.sender)?;
+
+    if let Some(filter) = filter {
+        config.asset_filter = filter;
+        CONFIG.save(deps.storage, &config)?;
+    }
+    for contract in add.iter() {
+        let contract = deps.api.addr_validate(contract)?;
+        ASSET_LIST.save(deps.storage, &contract, &Empty {})?;
+    }
+    for contract in remove.iter() {
+        let contract = deps.api.addr_validate(contract)?;
+        ASSET_LIST.remove(deps.storage, &contract);
+    }
+
+    Ok(Response::default()
+        .add_attribute("action", "update_asset_list")
+        .add_attribute("sender", info.sender.to_string())
+        .add_attribute("added", add.join(","))
+        .add_attribute("removed", remove.join(",")))
+}
+
+/// Refuse deposits leaving a bundle with more distinct assets than allowed
+fn check_bundle_size(storage: &dyn Storage, bundle_id: &str) -> Result<(), ContractError> {
+    let max = CONFIG.load(storage)?.max_bundle_assets;
+    let count = ASSET_COUNTS
+        .may_load(storage, bundle_id)?
+        .unwrap_or_default();
+    if count > max {
+        return Err(ContractError::BundleFull { max });
+    }
+    Ok(())
+}
+
+/// Refuse assets of contracts the asset list does not allow
+fn check_asset_allowed(storage: &dyn Storage, contract: &Addr) -> Result<(), ContractError> {
+    let listed = ASSET_LIST.has(storage, contract);
+    let allowed = match CONFIG.load(storage)?.asset_filter {
+        AssetFilter::Off {} => true,
+        AssetFilter::Allowlist {} => listed,
+        AssetFilter::Denylist {} => !listed,
+    };
+    if !allowed {
+        return Err(ContractError::AssetNotAllowed {
+            contract: contract.to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn check_not_paused(storage: &dyn Storage) -> Result<(), ContractError> {
+    if CONFIG.load(storage)?.paused {
+        return Err(ContractError::Paused {});
+    }
+    Ok(())
+}
+
+pub fn check_can_deposit(
+    storage: &dyn Storage,
+    bundle_id: &str,
+    token: &TokenInfo<Extension>,
+    sender: &str,
+) -> Result<(), ContractError> {
+    // owner, allowed depositors and, for open bundles, anyone can deposit
+    if token.owner == sender
+        || OPEN_BUNDLES.has(storage, bundle_id)
+        || ALLOWED_DEPOSITORS.has(storage, (bundle_id, &Addr::unchecked(sender)))
+    {
+        return Ok(());
+    }
+    Err(ContractError::Unauthorized {})
+}
+
+pub fn update_depositors(
+    deps: DepsMut,
+    info: MessageInfo,
+    bundle_id: String,
+    add: Vec<String>,
+    remove: Vec<String>,
+) -> Result<Response, ContractError> {
+    let token_info = Cw721Contract::<Extension, Empty>::default()
+        .tokens
+        .load(deps.storage, &bundle_id)?;
+    check_can_withdraw(&info, &token_info)?;
+
+    for depositor in add.iter() {
+        let depositor = deps.api.addr_validate(depositor)?;
+        ALLOWED_DEPOSITORS.save(deps.storage, (&bundle_id, &depositor), &Empty {})?;
+    }
+    for depositor in remove.iter() {
+        let depositor = deps.api.addr_validate(depositor)?;
+        ALLOWED_DEPOSITORS.remove(deps.storage, (&bundle_id, &depositor));
+    }
+
+    Ok(Response::default()
+        .add_attribute("action", "update_depositors")
+        .add_attribute("sender", info.sender)
+        .add_attribute("bundle_id", bundle_id)
+        .add_attribute("added", add.join(","))
+        .add_attribute("removed", remove.join(",")))
+}
+
+pub fn set_open_to_contributions(
+    deps: DepsMut,
+    info: MessageInfo,
+    bundle_id: String,
+    open: bool,
+) -> Result<Response, ContractError> {
+    let token_info = Cw721Contract::<Extension, Empty>::default()
+        .tokens
+        .load(deps.storage, &bundle_id)?;
+    check_can_withdraw(&info, &token_info)?;
+
+    if open {
+        OPEN_BUNDLES.save(deps.storage, &bundle_id, &Empty {})?;
+    } else {
+        OPEN_BUNDLES.remove(deps.storage, &bundle_id);
+    }
+
+    Ok(Response::default()
+        .add_attribute("action", "set_open_to_contributions")
+        .add_attribute("sender", info.sender)
+        .add_attribute("bundle_id", bundle_id)
+        .add_attribute("open", open.to_string()))
+}
+
+/// Drop the deposit rights granted by the previous owner of a bundle
+fn clear_deposit_rights(storage: &mut dyn Storage, bundle_id: &str) -> StdResult<()> {
+    OPEN_BUNDLES.remove(storage, bundle_id);
+    let depositors: Vec<Vec<u8>> = ALLOWED_DEPOSITORS
+        .prefix(bundle_id)
+        .keys(storage, None, None, Order::Ascending)
+        .collect();
+    for depositor in depositors {
+        let depositor = Addr::unchecked(String::from_utf8(depositor)?);
+        ALLOWED_DEPOSITORS.remove(storage, (bundle_id, &depositor));
+    }
+    Ok(())
+}
+
+fn record_contribution(
+    storage: &mut dyn Storage,
+    bundle_id: &str,
+    depositor: &Addr,
+) -> StdResult<()> {
+    CONTRIBUTORS.update(
+        storage,
+        (bundle_id, depositor),
+        |deposits| -> StdResult<_> { Ok(deposits.unwrap_or_default() + 1) },
+    )?;
+    Ok(())
+}
+
+fn clear_contributions(storage: &mut dyn Storage, bundle_id: &str) -> StdResult<()> {
+    let contributors: Vec<Vec<u8>> = CONTRIBUTORS
+        .prefix(bundle_id)
+        .keys(storage, None, None, Order::Ascending)
+        .collect();
+    for contributor in contributors {
+        let contributor = Addr::unchecked(String::from_utf8(contributor)?);
+        CONTRIBUTORS.remove(storage, (bundle_id, &contributor));
+    }
+    Ok(())
+}
+
+pub fn check_can_withdraw(
+    info: &MessageInfo,
+    token: &TokenInfo<Extension>,
+) -> Result<(), ContractError> {
+    // only owner can withdraw
+    if token.owner == info.sender {
+        return Ok(());
+    }
+    Err(ContractError::Unauthorized {})
+}
+
+/// Refuse withdrawals until `until`. An active lock can only be extended.
+pub fn lock(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    bundle_id: String,
+    until: Expiration,
+) -> Result<Response, ContractError> {
+    let token_info = Cw721Contract::<Extension, Empty>::default()
+        .tokens
+        .load(deps.storage, &bundle_id)?;
+    check_can_withdraw(&info, &token_info)?;
+
+    if until.is_expired(&env.block) {
+        return Err(ContractError::Expired {});
+    }
+    match LOCKS.may_load(deps.storage, &bundle_id)? {
+        Some(BundleLock::Sealed {}) => return Err(ContractError::BundleSealed {}),
+        Some(BundleLock::Until(current))
+            if !current.is_expired(&env.block)
+                && until.partial_cmp(&current) != Some(Ordering::Greater) =>
+        {
+            return Err(ContractError::LockNotExtended {})
+        }
+        _ => {}
+    }
+    LOCKS.save(deps.storage, &bundle_id, &BundleLock::Until(until))?;
+
+    Ok(Response::default()
+        .add_attribute("action", "lock")
+        .add_attribute("sender", info.sender)
+        .add_attribute("bundle_id", bundle_id)
+        .add_attribute("until", until.to_string()))
+}
+
+/// Permanently refuse withdrawals and deposits
+pub fn seal(
+    deps: DepsMut,
+    info: MessageInfo,
+    bundle_id: String,
+) -> Result<Response, ContractError> {
+    let token_info = Cw721Contract::<Extension, Empty>::default()
+        .tokens
+        .load(deps.storage, &bundle_id)?;
+    check_can_withdraw(&info, &token_info)?;
+    check_not_sealed(deps.storage, &bundle_id)?;
+
+    LOCKS.save(deps.storage, &bundle_id, &BundleLock::Sealed {})?;
+
+    Ok(Response::default()
+        .add_attribute("action", "seal")
+        .add_attribute("sender", info.sender)
+        .add_attribute("bundle_id", bundle_id))
+}
+
+fn check_unlocked(storage: &dyn Storage, env: &Env, bundle_id: &str) -> Result<(), ContractError> {
+    match LOCKS.may_load(storage, bundle_id)? {
+        Some(BundleLock::Sealed {}) => Err(ContractError::BundleSealed {}),
+        Some(BundleLock::Until(until)) if !until.is_expired(&env.block) => {
+            Err(ContractError::BundleLocked {})
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Refuse withdrawals while a spender is approved for the bundle, so a buyer filling a
+/// listing never receives a bundle emptied after it was listed
+fn check_not_approved(env: &Env, token: &TokenInfo<Extension>) -> Result<(), ContractError> {
+    if token
+        .approvals
+        .iter()
+        .any(|approval| !approval.is_expired(&env.block))
+    {
+        return Err(ContractError::BundleApproved {});
+    }
+    Ok(())
+}
+
+fn check_not_sealed(storage: &dyn Storage, bundle_id: &str) -> Result<(), ContractError> {
+    match LOCKS.may_load(storage, bundle_id)? {
+        Some(BundleLock::Sealed {}) => Err(ContractError::BundleSealed {}),
+        _ => Ok(()),
+    }
+}
+
+pub fn withdraw(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    bundle_id: String,
+    options: WithdrawOptions,
+) -> Result<Response, ContractError> {
+    let token_info = Cw721Contract::<Extension, Empty>::default()
+        .tokens
+        .load(deps.storage, &bundle_id)?;
+    check_can_withdraw(&info, &token_info)?;
+    check_unlocked(deps.storage, &env, &bundle_id)?;
+    check_not_approved(&env, &token_info)?;
+
+    if options.msg.is_some() && !options.use_send {
+        return Err(ContractError::MsgWithoutSend {});
+    }
+
+    let mut payout = if options.splits.is_empty() {
+        let recipient = match options.recipient {
+            Some(recipient) => deps.api.addr_validate(&recipient)?,
+            None => info.sender.clone(),
+        };
+        Payout::to(recipient)
+    } else {
+        if options.recipient.is_some() {
+            return Err(ContractError::InvalidWithdrawSplit {});
+        }
+        let mut total = 0u32;
+        let mut recipients = vec![];
+        for split in options.splits {
+            if split.bps == 0 {
+                return Err(ContractError::InvalidWithdrawSplit {});
+            }
+            total += u32::from(split.bps);
+            recipients.push((deps.api.addr_validate(&split.recipient)?, split.bps));
+        }
+        if total != 10_000 {
+            return Err(ContractError::InvalidWithdrawSplit {});
+        }
+        Payout {
+            recipients,
+            hook: None,
+        }
+    };
+    if options.use_send {
+        payout.hook = Some(options.msg.unwrap_or_default());
+    }
+
+    let mut withdraw_fee = WithdrawFee::load(deps.storage)?;
+    let (cw_transfer_cosmos_msgs, events) = release_bundle(
+        deps.storage,
+        &env,
+        &bundle_id,
+        &payout,
+        &mut withdraw_fee,
+        usize::MAX,
+    )?;
+
+    let recipients: Vec<&str> = payout
+        .recipients
+        .iter()
+        .map(|(recipient, _)| recipient.as_str())
+        .collect();
+    let res = Response::new()
+        .add_messages(cw_transfer_cosmos_msgs)
+        .add_events(events)
+        .add_attribute("action", "withdraw")
+        .add_attribute("recipient", recipients.join(","));
+    Ok(withdraw_fee.finish().add_to(res))
+}
+
+/// Withdraw up to `limit` assets, so bundles too large for one `Withdraw` can be drained
+pub fn withdraw_batch(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    bundle_id: String,
+    limit: u32,
+) -> Result<Response, ContractError> {
+    if limit == 0 {
+        return Err(ContractError::InvalidLimit {});
+    }
+    let token_info = Cw721Contract::<Extension, Empty>::default()
+        .tokens
+        .load(deps.storage, &bundle_id)?;
+    check_can_withdraw(&info, &token_info)?;
+    check_unlocked(deps.storage, &env, &bundle_id)?;
+    check_not_approved(&env, &token_info)?;
+
+    let mut withdraw_fee = WithdrawFee::load(deps.storage)?;
+    let (cw_transfer_cosmos_msgs, events) = release_bundle(
+        deps.storage,
+        &env,
+        &bundle_id,
+        &Payout::to(info.sender.clone()),
+        &mut withdraw_fee,
+        limit as usize,
+    )?;
+
+    let res = Response::new()
+        .add_messages(cw_transfer_cosmos_msgs)
+        .add_events(events)
+        .add_attribute("action", "withdraw_batch")
+        .add_attribute("bundle_id", bundle_id);
+    Ok(withdraw_fee.finish().add_to(res))
+}
+
+pub fn unbundle(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    bundle_id: String,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let cw721_contract = Cw721Contract::<Extension, Empty>::default();
+    let token_info = cw721_contract.tokens.load(deps.storage, &bundle_id)?;
+    check_can_withdraw(&info, &token_info)?;
+    check_unlocked(deps.storage, &env, &bundle_id)?;
+    check_not_approved(&env, &token_info)?;
+
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => info.sender.clone(),
+    };
+    let mut withdraw_fee = WithdrawFee::load(deps.storage)?;
+    let (cw_transfer_cosmos_msgs, events) = release_bundle(
+        deps.storage,
+        &env,
+        &bundle_id,
+        &Payout::to(recipient.clone()),
+        &mut withdraw_fee,
+        usize::MAX,
+    )?;
+
+    // cw721-base has no burn message yet, so the token is removed the same way one would
+    cw721_contract.tokens.remove(deps.storage, &bundle_id)?;
+    let count = cw721_contract.token_count(deps.storage)?;
+    cw721_contract
+        .token_count
+        .save(deps.storage, &(count - 1))?;
+    clear_deposit_rights(deps.storage, &bundle_id)?;
+    clear_contributions(deps.storage, &bundle_id)?;
+    LOCKS.remove(deps.storage, &bundle_id);
+    uncount_created_bundle(deps.storage, &bundle_id)?;
+
+    let res = Response::new()
+        .add_messages(cw_transfer_cosmos_msgs)
+        .add_events(events)
+        .add_attribute("action", "unbundle")
+        .add_attribute("sender", info.sender)
+        .add_attribute("recipient", recipient)
+        .add_attribute("bundle_id", bundle_id);
+    Ok(withdraw_fee.finish().add_to(res))
+}
+
+/// Where released assets go: recipients with their share in basis points, and the hook
+/// payload when tokens are sent to contracts
+struct Payout {
+    /// The first recipient also gets the cw721 tokens and rounding leftovers
+    recipients: Vec<(Addr, u16)>,
+    hook: Option<Binary>,
+}
+
+impl Payout {
+    fn to(recipient: Addr) -> Self {
+        Payout {
+            recipients: vec![(recipient, 10_000)],
+            hook: None,
+        }
+    }
+
+    /// The share of `amount` for each recipient, in the order of `recipients`
+    fn shares(&self, amount: Uint128) -> Vec<(&Addr, Uint128)> {
+        let mut shares: Vec<(&Addr, Uint128)> = self
+            .recipients
+            .iter()
+            .map(|(recipient, bps)| (recipient, amount.multiply_ratio(*bps, 10_000u128)))
+            .collect();
+        let paid = shares
+            .iter()
+            .fold(Uint128::zero(), |paid, (_, share)| paid + *share);
+        shares[0].1 += amount - paid;
+        shares
+    }
+
+    fn cw721_msg(&self, contract_address: &Addr, token_id: String) -> StdResult<CosmosMsg> {
+        let recipient = self.recipients[0].0.to_string();
+        let msg = match &self.hook {
+            Some(hook) => Cw721ExecuteMsg::SendNft {
+                contract: recipient,
+                token_id,
+                msg: hook.clone(),
+            },
+            None => Cw721ExecuteMsg::TransferNft {
+                recipient,
+                token_id,
+            },
+        };
+        Ok(WasmMsg::Execute {
+            contract_addr: contract_address.to_string(),
+            msg: to_binary(&msg)?,
+            funds: vec![],
+        }
+        .into())
+    }
+
+    fn cw20_msgs(&self, contract_address: &Addr, amount: Uint128) -> StdResult<Vec<CosmosMsg>> {
+        self.shares(amount)
+            .into_iter()
+            .filter(|(_, amount)| !amount.is_zero())
+            .map(|(recipient, amount)| {
+                let msg = match &self.hook {
+                    Some(hook) => Cw20ExecuteMsg::Send {
+                        contract: recipient.to_string(),
+                        amount,
+                        msg: hook.clone(),
+                    },
+                    None => Cw20ExecuteMsg::Transfer {
+                        recipient: recipient.to_string(),
+                        amount,
+                    },
+                };
+                Ok(WasmMsg::Execute {
+                    contract_addr: contract_address.to_string(),
+                    msg: to_binary(&msg)?,
+                    funds: vec![],
+                }
+                .into())
+            })
+            .collect()
+    }
+
+    fn cw1155_msgs(
+        &self,
+        env: &Env,
+        contract_address: &Addr,
+        batch: &[(String, Uint128)],
+    ) -> StdResult<Vec<CosmosMsg>> {
+        let mut batches: Vec<(&Addr, Vec<(String, Uint128)>)> = self
+            .recipients
+            .iter()
+            .map(|(recipient, _)| (recipient, vec![]))
+            .collect();
+        for (token_id, amount) in batch {
+            for (i, (_, share)) in self.shares(*amount).into_iter().enumerate() {
+                if !share.is_zero() {
+                    batches[i].1.push((token_id.clone(), share));
+                }
+            }
+        }
+        batches
+            .into_iter()
+            .filter(|(_, batch)| !batch.is_empty())
+            .map(|(recipient, batch)| {
+                let msg = Cw1155ExecuteMsg::BatchSendFrom {
+                    from: env.contract.address.to_string(),
+                    to: recipient.to_string(),
+                    batch,
+                    msg: self.hook.clone(),
+                };
+                Ok(WasmMsg::Execute {
+                    contract_addr: contract_address.to_string(),
+                    msg: to_binary(&msg)?,
+                    funds: vec![],
+                }
+                .into())
+            })
+            .collect()
+    }
+
+    fn native_msgs(&self, coins: &[Coin]) -> Vec<CosmosMsg> {
+        let mut amounts: Vec<(&Addr, Vec<Coin>)> = self
+            .recipients
+            .iter()
+            .map(|(recipient, _)| (recipient, vec![]))
+            .collect();
+        for coin in coins {
+            for (i, (_, share)) in self.shares(coin.amount).into_iter().enumerate() {
+                if !share.is_zero() {
+                    amounts[i].1.push(Coin {
+                        denom: coin.denom.clone(),
+                        amount: share,
+                    });
+                }
+            }
+        }
+        amounts
+            .into_iter()
+            .filter(|(_, amount)| !amount.is_empty())
+            .map(|(recipient, amount)| {
+                BankMsg::Send {
+                    to_address: recipient.to_string(),
+                    amount,
+                }
+                .into()
+            })
+            .collect()
+    }
+}
+
+/// Remove up to `limit` assets from the bundle and build the messages paying them out,
+/// less the withdraw fee, with an event per asset removed
+fn release_bundle(
+    storage: &mut dyn Storage,
+    env: &Env,
+    bundle_id: &str,
+    payout: &Payout,
+    withdraw_fee: &mut WithdrawFee,
+    limit: usize,
+) -> StdResult<(Vec<CosmosMsg>, Vec<Event>)> {
+    let contents = load_bundle_page(storage, bundle_id, limit)?;
+    // amounts leave the bundle in full, the withdraw fee is itemized separately
+    let events = contents_events(WITHDRAW_EVENT, bundle_id, &contents);
+    let mut cw_transfer_cosmos_msgs = vec![];
+
+    for asset in contents.cw721 {
+        cw721_bundle().remove(
+            storage,
+            (bundle_id, &asset.contract_address, &asset.token_id),
+        )?;
+        asset_removed(storage, bundle_id)?;
+        cw_transfer_cosmos_msgs.push(payout.cw721_msg(&asset.contract_address, asset.token_id)?);
+    }
+
+    for asset in contents.cw20 {
+        cw20_bundle().remove(storage, (bundle_id, &asset.contract_address))?;
+        asset_removed(storage, bundle_id)?;
+        let amount = withdraw_fee.take_cw20(&asset.contract_address, asset.amount)?;
+        cw_transfer_cosmos_msgs.extend(payout.cw20_msgs(&asset.contract_address, amount)?);
+    }
+
+    // tokens have to be sent back by the cw1155 contract they came from
+    let mut cw1155_batches: BTreeMap<Addr, Vec<(String, Uint128)>> = BTreeMap::new();
+    for asset in contents.cw1155 {
+        cw1155_bundle().remove(
+            storage,
+            (bundle_id, &asset.contract_address, &asset.token_id),
+        )?;
+        asset_removed(storage, bundle_id)?;
+        let amount = withdraw_fee.take_cw1155(
+            env,
+            &asset.contract_address,
+            &asset.token_id,
+            asset.amount,
+        )?;
+        if amount.is_zero() {
+            continue;
+        }
+        cw1155_batches
+            .entry(asset.contract_address)
+            .or_default()
+            .push((asset.token_id, amount));
+    }
+    for (contract_address, cw1155_batch) in cw1155_batches {
+        cw_transfer_cosmos_msgs.extend(payout.cw1155_msgs(
+            env,
+            &contract_address,
+            &cw1155_batch,
+        )?);
+    }
+
+    for coin in contents.native.iter() {
+        NATIVE_BUNDLE.remove(storage, (bundle_id, &coin.denom));
+        asset_removed(storage, bundle_id)?;
+    }
+    let native = withdraw_fee.take_native(contents.native);
+    cw_transfer_cosmos_msgs.extend(payout.native_msgs(&native));
+
+    Ok((cw_transfer_cosmos_msgs, events))
+}
+
+pub fn withdraw_assets(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    bundle_id: String,
+    assets: BundleAssets,
+    discard: bool,
+) -> Result<Response, ContractError> {
+    let token_info = Cw721Contract::<Extension, Empty>::default()
+        .tokens
+        .load(deps.storage, &bundle_id)?;
+    check_can_withdraw(&info, &token_info)?;
+    check_unlocked(deps.storage, &env, &bundle_id)?;
+    check_not_approved(&env, &token_info)?;
+
+    // refuse selections that would move nothing
+    if assets.native.is_empty()
+        && assets.cw20.is_empty()
+        && assets.cw721.is_empty()
+        && assets.cw1155.is_empty()
+    {
+        return Err(ContractError::NoFunds {});
+    }
+    if assets.native.iter().any(|coin| coin.amount.is_zero())
+        || assets.cw20.iter().any(|asset| asset.amount.is_zero())
+        || assets.cw1155.iter().any(|asset| asset.amount.is_zero())
+    {
+        return Err(ContractError::NoFunds {});
+    }
+
+    let mut withdraw_fee = WithdrawFee::load(deps.storage)?;
+    let mut cw_transfer_cosmos_msgs = vec![];
+    let mut events = vec![];
+
+    for asset in assets.cw721 {
+        let contract_address = deps.api.addr_validate(&asset.contract_address)?;
+        let key = (
+            bundle_id.as_str(),
+            &contract_address,
+            asset.token_id.as_str(),
+        );
+        cw721_bundle()
+            .may_load(deps.storage, key)?
+            .ok_or(ContractError::AssetNotFound {})?;
+        cw721_bundle().remove(deps.storage, key)?;
+        asset_removed(deps.storage, &bundle_id)?;
+        events.push(asset_event(
+            WITHDRAW_EVENT,
+            &bundle_id,
+            "cw721",
+            contract_address.as_str(),
+            &asset.token_id,
+            Uint128::new(1),
+        ));
+        if discard {
+            continue;
+        }
+
+        let transfer_cw721_msg = Cw721ExecuteMsg::TransferNft {
+            recipient: info.sender.to_string(),
+            token_id: asset.token_id,
+        };
+        let exec_cw721_transfer = WasmMsg::Execute {
+            contract_addr: contract_address.to_string(),
+            msg: to_binary(&transfer_cw721_msg)?,
+            funds: vec![],
+        };
+        cw_transfer_cosmos_msgs.push(exec_cw721_transfer.into());
+    }
+
+    for asset in assets.cw20 {
+        let contract_address = deps.api.addr_validate(&asset.contract_address)?;
+        subtract_cw20(deps.storage, &bundle_id, &contract_address, asset.amount)?;
+        events.push(asset_event(
+            WITHDRAW_EVENT,
+            &bundle_id,
+            "cw20",
+            contract_address.as_str(),
+            "",
+            asset.amount,
+        ));
+        if discard {
+            continue;
+        }
+        let amount = withdraw_fee.take_cw20(&contract_address, asset.amount)?;
+        if amount.is_zero() {
+            continue;
+        }
+
+        let transfer_cw20_msg = Cw20ExecuteMsg::Transfer {
+            recipient: info.sender.to_string(),
+            amount,
+        };
+        let exec_cw20_transfer = WasmMsg::Execute {
+            contract_addr: contract_address.to_string(),
+            msg: to_binary(&transfer_cw20_msg)?,
+            funds: vec![],
+        };
+        cw_transfer_cosmos_msgs.push(exec_cw20_transfer.into());
+    }
+
+    for asset in assets.cw1155 {
+        let contract_address = deps.api.addr_validate(&asset.contract_address)?;
+        subtract_cw1155(
+            deps.storage,
+            &bundle_id,
+            &contract_address,
+            &asset.token_id,
+            asset.amount,
+        )?;
+        events.push(asset_event(
+            WITHDRAW_EVENT,
+            &bundle_id,
+            "cw1155",
+            contract_address.as_str(),
+            &asset.token_id,
+            asset.amount,
+        ));
+        if discard {
+            continue;
+        }
+        let amount =
+            withdraw_fee.take_cw1155(&env, &contract_address, &asset.token_id, asset.amount)?;
+        if amount.is_zero() {
+            continue;
+        }
+
+        let transfer_cw1155_msg = Cw1155ExecuteMsg::SendFrom {
+            from: env.contract.address.to_string(),
+            to: info.sender.to_string(),
+            token_id: asset.token_id,
+            value: amount,
+            msg: None,
+        };
+        let exec_cw1155_transfer = WasmMsg::Execute {
+            contract_addr: contract_address.to_string(),
+            msg: to_binary(&transfer_cw1155_msg)?,
+            funds: vec![],
+        };
+        cw_transfer_cosmos_msgs.push(exec_cw1155_transfer.into());
+    }
+
+    for coin in assets.native.iter() {
+        subtract_native(deps.storage, &bundle_id, coin)?;
+        events.push(asset_event(
+            WITHDRAW_EVENT,
+            &bundle_id,
+            "native",
+            &coin.denom,
+            "",
+            coin.amount,
+        ));
+    }
+    let native = if discard {
+        vec![]
+    } else {
+        withdraw_fee.take_native(assets.native)
+    };
+    if !native.is_empty() {
+        let native_transfer_cosmos_msg: CosmosMsg = BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: native,
+        }
+        .into();
+        cw_transfer_cosmos_msgs.push(native_transfer_cosmos_msg);
+    }
+
+    let res = Response::new()
+        .add_messages(cw_transfer_cosmos_msgs)
+        .add_events(events)
+        .add_attribute(
+            "action",
+            if discard {
+                "discard_assets"
+            } else {
+                "withdraw_assets"
+            },
+        )
+        .add_attribute("bundle_id", bundle_id);
+    Ok(withdraw_fee.finish().add_to(res))
+}
+
+/// Count a distinct asset entering a bundle
+fn asset_added(storage: &mut dyn Storage, bundle_id: &str) -> StdResult<()> {
+    ASSET_COUNTS.update(storage, bundle_id, |count| -> StdResult<_> {
+        Ok(count.unwrap_or_default() + 1)
+    })?;
+    Ok(())
+}
+
+/// Count a distinct asset leaving a bundle
+fn asset_removed(storage: &mut dyn Storage, bundle_id: &str) -> StdResult<()> {
+    let count = ASSET_COUNTS
+        .may_load(storage, bundle_id)?
+        .unwrap_or_default();
+    if count <= 1 {
+        ASSET_COUNTS.remove(storage, bundle_id);
+    } else {
+        ASSET_COUNTS.save(storage, bundle_id, &(count - 1))?;
+    }
+    Ok(())
+}
+
+pub(crate) fn add_native(storage: &mut dyn Storage, bundle_id: &str, coin: &Coin) -> StdResult<()> {
+    let mut added = false;
+    NATIVE_BUNDLE.update(
+        storage,
+        (bundle_id, coin.denom.as_str()),
+        |amount| -> StdResult<_> {
+            added = amount.is_none();
+            Ok(amount.unwrap_or_default().checked_add(coin.amount)?)
+        },
+    )?;
+    if added {
+        asset_added(storage, bundle_id)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn add_cw20(
+    storage: &mut dyn Storage,
+    bundle_id: &str,
+    contract_address: &Addr,
+    amount: Uint128,
+) -> StdResult<()> {
+    let mut added = false;
+    cw20_bundle().update(
+        storage,
+        (bundle_id, contract_address),
+        |asset| -> StdResult<_> {
+            added = asset.is_none();
+            let mut asset = asset.unwrap_or(CW20Wrapper {
+                contract_address: contract_address.clone(),
+                amount: Uint128::zero(),
+            });
+            asset.amount = asset.amount.checked_add(amount)?;
+            Ok(asset)
+        },
+    )?;
+    if added {
+        asset_added(storage, bundle_id)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn add_cw1155(
+    storage: &mut dyn Storage,
+    bundle_id: &str,
+    contract_address: &Addr,
+    token_id: &str,
+    amount: Uint128,
+) -> StdResult<()> {
+    let mut added = false;
+    cw1155_bundle().update(
+        storage,
+        (bundle_id, contract_address, token_id),
+        |asset| -> StdResult<_> {
+            added = asset.is_none();
+            let mut asset = asset.unwrap_or(CW1155Wrapper {
+                contract_address: contract_address.clone(),
+                token_id: token_id.to_string(),
+                amount: Uint128::zero(),
+            });
+            asset.amount = asset.amount.checked_add(amount)?;
+            Ok(asset)
+        },
+    )?;
+    if added {
+        asset_added(storage, bundle_id)?;
+    }
+    Ok(())
+}
+
+fn subtract_cw20(
+    storage: &mut dyn Storage,
+    bundle_id: &str,
+    contract_address: &Addr,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    let key = (bundle_id, contract_address);
+    let mut asset = cw20_bundle()
+        .may_load(storage, key)?
+        .ok_or(ContractError::InsufficientBalance {})?;
+    asset.amount = asset
+        .amount
+        .checked_sub(amount)
+        .map_err(|_| ContractError::InsufficientBalance {})?;
+    if asset.amount.is_zero() {
+        cw20_bundle().remove(storage, key)?;
+        asset_removed(storage, bundle_id)?;
+    } else {
+        cw20_bundle().save(storage, key, &asset)?;
+    }
+    Ok(())
+}
+
+fn subtract_cw1155(
+    storage: &mut dyn Storage,
+    bundle_id: &str,
+    contract_address: &Addr,
+    token_id: &str,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    let key = (bundle_id, contract_address, token_id);
+    let mut asset = cw1155_bundle()
+        .may_load(storage, key)?
+        .ok_or(ContractError::InsufficientBalance {})?;
+    asset.amount = asset
+        .amount
+        .checked_sub(amount)
+        .map_err(|_| ContractError::InsufficientBalance {})?;
+    if asset.amount.is_zero() {
+        cw1155_bundle().remove(storage, key)?;
+        asset_removed(storage, bundle_id)?;
+    } else {
+        cw1155_bundle().save(storage, key, &asset)?;
+    }
+    Ok(())
+}
+
+fn subtract_native(
+    storage: &mut dyn Storage,
+    bundle_id: &str,
+    coin: &Coin,
+) -> Result<(), ContractError> {
+    let key = (bundle_id, coin.denom.as_str());
+    let amount = NATIVE_BUNDLE
+        .may_load(storage, key)?
+        .unwrap_or_default()
+        .checked_sub(coin.amount)
+        .map_err(|_| ContractError::InsufficientBalance {})?;
+    if amount.is_zero() {
+        NATIVE_BUNDLE.remove(storage, key);
+        asset_removed(storage, bundle_id)?;
+    } else {
+        NATIVE_BUNDLE.save(storage, key, &amount)?;
+    }
+    Ok(())
+}
+
+/// Record a cw721 token in a bundle. Bundles of this contract may be nested in each
+/// other as long as no cycle forms and the nesting stays within the configured depth.
+pub(crate) fn add_cw721(
+    storage: &mut dyn Storage,
+    contract: &Addr,
+    bundle_id: &str,
+    asset: &CW721Wrapper,
+) -> Result<(), ContractError> {
+    check_not_bundled(storage, &asset.contract_address, &asset.token_id)?;
+    if asset.contract_address == *contract {
+        check_can_nest(storage, contract, bundle_id, &asset.token_id)?;
+    }
+    cw721_bundle().save(
+        storage,
+        (bundle_id, &asset.contract_address, &asset.token_id),
+        asset,
+    )?;
+    asset_added(storage, bundle_id)?;
+    Ok(())
+}
+
+/// A cw721 token can only be held by one bundle at a time
+fn check_not_bundled(
+    storage: &dyn Storage,
+    contract_address: &Addr,
+    token_id: &str,
+) -> Result<(), ContractError> {
+    let bundled = cw721_bundle()
+        .idx
+        .token
+        .prefix((contract_address.clone(), token_id.to_string()))
+        .keys(storage, None, None, Order::Ascending)
+        .next()
+        .is_some();
+    if bundled {
+        return Err(ContractError::AssetAlreadyBundled {});
+    }
+    Ok(())
+}
+
+fn check_can_nest(
+    storage: &dyn Storage,
+    contract: &Addr,
+    parent_id: &str,
+    child_id: &str,
+) -> Result<(), ContractError> {
+    let max_depth = CONFIG.load(storage)?.max_nesting_depth;
+
+    // the child must not be the parent or one of its ancestors
+    let mut depth = 1;
+    let mut ancestor = Some(parent_id.to_string());
+    while let Some(bundle_id) = ancestor {
+        if bundle_id == child_id {
+            return Err(ContractError::NestingCycle {});
+        }
+        ancestor = parent_bundle(storage, contract, &bundle_id)?;
+        if ancestor.is_some() {
+            depth += 1;
+        }
+    }
+
+    if depth + nesting_height(storage, contract, child_id)? > max_depth {
+        return Err(ContractError::NestingTooDeep { max: max_depth });
+    }
+    Ok(())
+}
+
+/// The bundle holding the given bundle, if any. A bundle recorded under several parents
+/// is refused, as depth and cycle checks could follow the wrong chain.
+fn parent_bundle(
+    storage: &dyn Storage,
+    contract: &Addr,
+    bundle_id: &str,
+) -> StdResult<Option<String>> {
+    let keys: Vec<Vec<u8>> = cw721_bundle()
+        .idx
+        .token
+        .prefix((contract.clone(), bundle_id.to_string()))
+        .keys(storage, None, None, Order::Ascending)
+        .take(2)
+        .collect();
+    match keys.as_slice() {
+        [] => Ok(None),
+        [key] => bundle_id_from_key(key).map(Some),
+        _ => Err(StdError::generic_err(format!(
+            "Bundle {} is held by more than one bundle",
+            bundle_id
+        ))),
+    }
+}
+
+/// Levels of bundles nested below the given bundle
+fn nesting_height(storage: &dyn Storage, contract: &Addr, bundle_id: &str) -> StdResult<u32> {
+    let mut height = 0;
+    for child_id in nested_bundle_ids(storage, contract, bundle_id)? {
+        height = height.max(1 + nesting_height(storage, contract, &child_id)?);
+    }
+    Ok(height)
+}
+
+fn nested_bundle_ids(
+    storage: &dyn Storage,
+    contract: &Addr,
+    bundle_id: &str,
+) -> StdResult<Vec<String>> {
+    cw721_bundle()
+        .prefix((bundle_id, contract))
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, asset)| asset.token_id))
+        .collect()
+}
+
+/// Contents of the bundle along with those of every bundle nested in it
+pub(crate) fn load_nested_contents(
+    storage: &dyn Storage,
+    contract: &Addr,
+    bundle_id: &str,
+) -> StdResult<BundleContentsResponse> {
+    let mut contents = load_bundle_contents(storage, bundle_id)?;
+    contents.nested = nested_bundle_ids(storage, contract, bundle_id)?
+        .into_iter()
+        .map(|child_id| {
+            Ok(NestedBundle {
+                contents: load_nested_contents(storage, contract, &child_id)?,
+                bundle_id: child_id,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(contents)
+}
+
+pub(crate) fn load_bundle_contents(
+    storage: &dyn Storage,
+    bundle_id: &str,
+) -> StdResult<BundleContentsResponse> {
+    load_bundle_page(storage, bundle_id, usize::MAX)
+}
+
+/// Load up to `limit` assets of a bundle, taking cw721, cw20, cw1155 and native
+/// entries in that order
+fn load_bundle_page(
+    storage: &dyn Storage,
+    bundle_id: &str,
+    limit: usize,
+) -> StdResult<BundleContentsResponse> {
+    let cw721 = cw721_bundle()
+        .sub_prefix(bundle_id)
+        .range(storage, None, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, asset)| asset))
+        .collect::<StdResult<Vec<_>>>()?;
+    let limit = limit - cw721.len();
+    let cw20 = cw20_bundle()
+        .prefix(bundle_id)
+        .range(storage, None, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, asset)| asset))
+        .collect::<StdResult<Vec<_>>>()?;
+    let limit = limit - cw20.len();
+    let cw1155 = cw1155_bundle()
+        .sub_prefix(bundle_id)
+        .range(storage, None, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, asset)| asset))
+        .collect::<StdResult<Vec<_>>>()?;
+    let limit = limit - cw1155.len();
+    let native = NATIVE_BUNDLE
+        .prefix(bundle_id)
+        .range(storage, None, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (denom, amount) = item?;
+            Ok(Coin {
+                denom: String::from_utf8(denom)?,
+                amount,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(BundleContentsResponse {
+        native,
+        cw20,
+        cw721,
+        cw1155,
+        nested: vec![],
+    })
+}
+
+/// Record the asset of a `DepositFromApprovals` transfer that went through
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    let key = U64Key::new(msg.id);
+    let deposit = PENDING_DEPOSITS.load(deps.storage, key.clone())?;
+    PENDING_DEPOSITS.remove(deps.storage, key);
+
+    let bundle_id = deposit.bundle_id;
+    let (contract_address, event) = match deposit.asset {
+        PendingAsset::Cw20(asset) => {
+            add_cw20(
+                deps.storage,
+                &bundle_id,
+                &asset.contract_address,
+                asset.amount,
+            )?;
+            let event = asset_event(
+                DEPOSIT_EVENT,
+                &bundle_id,
+                "cw20",
+                asset.contract_address.as_str(),
+                "",
+                asset.amount,
+            );
+            (asset.contract_address, event)
+        }
+        PendingAsset::Cw721(asset) => {
+            add_cw721(deps.storage, &env.contract.address, &bundle_id, &asset)?;
+            let event = asset_event(
+                DEPOSIT_EVENT,
+                &bundle_id,
+                "cw721",
+                asset.contract_address.as_str(),
+                &asset.token_id,
+                Uint128::new(1),
+            );
+            (asset.contract_address, event)
+        }
+        PendingAsset::Cw1155(asset) => {
+            add_cw1155(
+                deps.storage,
+                &bundle_id,
+                &asset.contract_address,
+                &asset.token_id,
+                asset.amount,
+            )?;
+            let event = asset_event(
+                DEPOSIT_EVENT,
+                &bundle_id,
+                "cw1155",
+                asset.contract_address.as_str(),
+                &asset.token_id,
+                asset.amount,
+            );
+            (asset.contract_address, event)
+        }
+    };
+    check_bundle_size(deps.storage, &bundle_id)?;
+    record_contribution(deps.storage, &bundle_id, &deposit.depositor)?;
+
+    Ok(Response::default()
+        .add_event(event)
+        .add_attribute("action", "record_deposit")
+        .add_attribute("contract_sender", contract_address.to_string())
+        .add_attribute("bundle_id", bundle_id))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let stored = get_contract_version(deps.storage)?;
+    let from_version = match stored.contract.as_str() {
+        CONTRACT_NAME => stored.version.parse::<Version>()?,
+        LEGACY_CONTRACT_NAME => Version::new(0, 1, 0),
+        _ => {
+            return Err(ContractError::InvalidMigrationContract {
+                contract: stored.contract,
+            })
+        }
+    };
+    let to_version = CONTRACT_VERSION.parse::<Version>()?;
+    if from_version > to_version {
+        return Err(ContractError::CannotDowngrade {
+            from: from_version.to_string(),
+            to: to_version.to_string(),
+        });
+    }
+
+    migrations::upgrade_storage(deps.storage, &from_version)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("from_version", from_version.to_string())
+        .add_attribute("to_version", CONTRACT_VERSION))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&CONFIG.load(deps.storage)?),
+        QueryMsg::Fees {} => to_binary(&CONFIG.load(deps.storage)?.fees),
+        QueryMsg::Paused {} => to_binary(&PausedResponse {
+            paused: CONFIG.load(deps.storage)?.paused,
+        }),
+        QueryMsg::RoleMembers {
+            role,
+            start_after,
+            limit,
+        } => to_binary(&query_role_members(deps, role, start_after, limit)?),
+        QueryMsg::PendingTransfers {} => to_binary(&PendingTransfersResponse {
+            admin: PENDING_ADMIN.may_load(deps.storage)?,
+            minter: PENDING_MINTER.may_load(deps.storage)?,
+        }),
+        QueryMsg::AssetList { start_after, limit } => {
+            to_binary(&query_asset_list(deps, start_after, limit)?)
+        }
+        QueryMsg::BundleLock { bundle_id } => to_binary(&query_bundle_lock(deps, env, bundle_id)?),
+        QueryMsg::BundleDepositors {
+            bundle_id,
+            start_after,
+            limit,
+        } => to_binary(&query_bundle_depositors(
+            deps,
+            bundle_id,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::BundleContributors {
+            bundle_id,
+            start_after,
+            limit,
+        } => to_binary(&query_bundle_contributors(
+            deps,
+            bundle_id,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::BundleContents {
+            bundle_id,
+            recursive,
+        } => to_binary(&query_bundle_contents(
+            deps,
+            env,
+            bundle_id,
+            recursive.unwrap_or(false),
+        )?),
+        QueryMsg::BundleInfo {
+            bundle_id,
+            include_expired,
+        } => to_binary(&query_bundle_info(
+            deps,
+            env,
+            bundle_id,
+            include_expired.unwrap_or(false),
+        )?),
+        QueryMsg::BundlesInfo {
+            bundle_ids,
+            include_expired,
+        } => to_binary(&query_bundles_info(
+            deps,
+            env,
+            bundle_ids,
+            include_expired.unwrap_or(false),
+        )?),
+        QueryMsg::BundlesContaining {
+            contract_address,
+            token_id,
+            start_after,
+            limit,
+        } => to_binary(&query_bundles_containing(
+            deps,
+            contract_address,
+            token_id,
+            start_after,
+            limit,
+        )?),
+        _ => {
+            let msg = cw721_query_msg::try_from(msg)?;
+            let response = Cw721Contract::<Extension, Empty>::default().query(deps, env, msg)?;
+            Ok(response)
+        }
+    }
+}
+
+pub fn query_bundle_contents(
+    deps: Deps,
+    env: Env,
+    bundle_id: String,
+    recursive: bool,
+) -> StdResult<BundleContentsResponse> {
+    // error if the bundle does not exist
+    Cw721Contract::<Extension, Empty>::default()
+        .tokens
+        .load(deps.storage, &bundle_id)?;
+
+    if recursive {
+        load_nested_contents(deps.storage, &env.contract.address, &bundle_id)
+    } else {
+        load_bundle_contents(deps.storage, &bundle_id)
+    }
+}
+
+pub fn query_bundle_lock(deps: Deps, env: Env, bundle_id: String) -> StdResult<BundleLockResponse> {
+    // error if the bundle does not exist
+    Cw721Contract::<Extension, Empty>::default()
+        .tokens
+        .load(deps.storage, &bundle_id)?;
+
+    let lock = LOCKS.may_load(deps.storage, &bundle_id)?;
+    let active = match &lock {
+        Some(BundleLock::Sealed {}) => true,
+        Some(BundleLock::Until(until)) => !until.is_expired(&env.block),
+        None => false,
+    };
+    Ok(BundleLockResponse { lock, active })
+}
+
+pub fn query_role_members(
+    deps: Deps,
+    role: Role,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<RoleMembersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let members = ROLES
+        .prefix(role.as_str())
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|member| String::from_utf8(member).map_err(StdError::from))
+        .collect::<StdResult<_>>()?;
+    Ok(RoleMembersResponse { members })
+}
+
+pub fn query_asset_list(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<AssetListResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let contracts = ASSET_LIST
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|contract| String::from_utf8(contract).map_err(StdError::from))
+        .collect::<StdResult<_>>()?;
+    Ok(AssetListResponse {
+        filter: CONFIG.load(deps.storage)?.asset_filter,
+        contracts,
+    })
+}
+
+pub fn query_bundle_depositors(
+    deps: Deps,
+    bundle_id: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<BundleDepositorsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let depositors = ALLOWED_DEPOSITORS
+        .prefix(&bundle_id)
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|depositor| String::from_utf8(depositor).map_err(StdError::from))
+        .collect::<StdResult<_>>()?;
+    Ok(BundleDepositorsResponse {
+        open: OPEN_BUNDLES.has(deps.storage, &bundle_id),
+        depositors,
+    })
+}
+
+pub fn query_bundle_contributors(
+    deps: Deps,
+    bundle_id: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<BundleContributorsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let contributors = CONTRIBUTORS
+        .prefix(&bundle_id)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (address, deposits) = item?;
+            Ok(Contributor {
+                address: String::from_utf8(address)?,
+                deposits,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(BundleContributorsResponse { contributors })
+}
+
+pub fn query_bundle_info(
+    deps: Deps,
+    env: Env,
+    bundle_id: String,
+    include_expired: bool,
+) -> StdResult<BundleInfoResponse> {
+    let info = Cw721Contract::<Extension, Empty>::default().all_nft_info(
+        deps,
+        env,
+        bundle_id.clone(),
+        include_expired,
+    )?;
+    let contents = load_bundle_contents(deps.storage, &bundle_id)?;
+    Ok(BundleInfoResponse {
+        bundle_id,
+        info,
+        contents,
+    })
+}
+
+pub fn query_bundles_info(
+    deps: Deps,
+    env: Env,
+    bundle_ids: Vec<String>,
+    include_expired: bool,
+) -> StdResult<BundlesInfoResponse> {
+    // every bundle expands its whole contents, so keep one query within the gas limit
+    if bundle_ids.len() > MAX_LIMIT as usize {
+        return Err(StdError::generic_err(format!(
+            "At most {} bundles can be queried at once",
+            MAX_LIMIT
+        )));
+    }
+    let bundles = bundle_ids
+        .into_iter()
+        .map(|bundle_id| query_bundle_info(deps, env.clone(), bundle_id, include_expired))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(BundlesInfoResponse { bundles })
+}
+
+pub fn query_bundles_containing(
+    deps: Deps,
+    contract_address: String,
+    token_id: Option<String>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<BundlesResponse> {
+    let contract_address = deps.api.addr_validate(&contract_address)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    // the asset is fixed by the index prefix, so the rest of the index key is the asset's pk
+    let keys = match token_id {
+        None => {
+            let start = start_after.map(|bundle_id| {
+                Bound::exclusive((bundle_id.as_str(), &contract_address).joined_key())
+            });
+            cw20_bundle()
+                .idx
+                .contract
+                .prefix(contract_address)
+                .keys(deps.storage, start, None, Order::Ascending)
+                .take(limit)
+                .collect::<Vec<_>>()
+        }
+        Some(token_id) => {
+            let start = || {
+                start_after.as_ref().map(|bundle_id| {
+                    Bound::exclusive(
+                        (bundle_id.as_str(), &contract_address, token_id.as_str()).joined_key(),
+                    )
+                })
+            };
+            let prefix = (contract_address.clone(), token_id.clone());
+            // a token lives in either the cw721 or the cw1155 map, merge both in key order
+            let mut keys: Vec<Vec<u8>> = cw721_bundle()
+                .idx
+                .token
+                .prefix(prefix.clone())
+                .keys(deps.storage, start(), None, Order::Ascending)
+                .take(limit)
+                .chain(
+                    cw1155_bundle()
+                        .idx
+                        .token
+                        .prefix(prefix)
+                        .keys(deps.storage, start(), None, Order::Ascending)
+                        .take(limit),
+                )
+                .collect();
+            keys.sort();
+            keys.dedup();
+            keys.truncate(limit);
+            keys
+        }
+    };
+
+    let bundle_ids = keys
+        .iter()
+        .map(|key| bundle_id_from_key(key))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(BundlesResponse { bundle_ids })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::{Cw1155Asset, Cw20Asset, Cw721Asset, WithdrawSplit};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi, MockStorage};
+    use cosmwasm_std::{
+        attr, coin, coins, from_binary, from_slice, ContractResult, OwnedDeps, Querier,
+        QuerierResult, QueryRequest, SubMsg, SubMsgExecutionResponse, SystemResult, WasmQuery,
+    };
+    use cw721::NumTokensResponse;
+    use cw721_base::msg::{MintMsg as Cw721MintMsg, MinterResponse};
+    use cw721_base::{Cw721Contract, Extension};
+
+    const TOKEN_ID: &str = "a";
+    const MINTER: &str = "minter_address";
+    const ASSET_CONTRACTS: [&str; 10] = [
+        CONTRACT,
+        MINTER,
+        "cw20_contract",
+        "other_token",
+        "third_token",
+        "cw721_contract",
+        "nft_contract",
+        "cw1155_contract",
+        "cw1155_one",
+        "cw1155_two",
+    ];
+    const ALICE: &str = "alice_address";
+    const CONTRACT: &str = "contract_address";
+    const ADMIN: &str = "admin_address";
+
+    fn setup_contract(mut deps: DepsMut) {
+        let msg = InstantiateMsg {
+            name: "CW Bundled Asset".into(),
+            symbol: "CWBUNDLE".into(),
+            minter: MINTER.into(),
+            admin: Some(ADMIN.into()),
+        };
+        let info = mock_info(MINTER, &[]);
+        let res = instantiate(deps.branch(), mock_env(), info, msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // the mock asset contracts deposit through their hooks
+        let msg = ExecuteMsg::UpdateAssetList {
+            filter: None,
+            add: ASSET_CONTRACTS
+                .iter()
+                .map(|contract| contract.to_string())
+                .collect(),
+            remove: vec![],
+        };
+        execute(deps, mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    }
+
+    fn mint_bundle(deps: DepsMut, token_id: &str, owner: &str) {
+        let info = mock_info(MINTER, &[]);
+        let mint_msg = cw721_execute_msg::Mint(Cw721MintMsg {
+            token_id: token_id.into(),
+            owner: owner.into(),
+            extension: None,
+            token_uri: None,
+        });
+        Cw721Contract::<Extension, Empty>::default()
+            .execute(deps, mock_env(), info, mint_msg)
+            .unwrap();
+    }
+
+    /// Answers cw721 `OwnerOf` queries from a list of (contract, token_id, owner)
+    struct Cw721OwnerQuerier {
+        owners: Vec<(&'static str, &'static str, String)>,
+    }
+
+    impl Querier for Cw721OwnerQuerier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            let (contract_addr, msg) = match from_slice(bin_request).unwrap() {
+                QueryRequest::<Empty>::Wasm(WasmQuery::Smart { contract_addr, msg }) => {
+                    (contract_addr, msg)
+                }
+                request => panic!("unexpected query {:?}", request),
+            };
+            let token_id = match from_binary(&msg).unwrap() {
+                Cw721QueryMsg::OwnerOf { token_id, .. } => token_id,
+                msg => panic!("unexpected cw721 query {:?}", msg),
+            };
+            let owner = self
+                .owners
+                .iter()
+                .find(|(contract, token, _)| *contract == contract_addr && *token == token_id);
+            let result = match owner {
+                Some((_, _, owner)) => to_binary(&OwnerOfResponse {
+                    owner: owner.clone(),
+                    approvals: vec![],
+                })
+                .into(),
+                None => ContractResult::Err("token not found".into()),
+            };
+            SystemResult::Ok(result)
+        }
+    }
+
+    fn dependencies_with_owners(
+        owners: Vec<(&'static str, &'static str, String)>,
+    ) -> OwnedDeps<MockStorage, MockApi, Cw721OwnerQuerier> {
+        OwnedDeps {
+            storage: MockStorage::default(),
+            api: MockApi::default(),
+            querier: Cw721OwnerQuerier { owners },
+        }
+    }
+
+    #[test]
+    fn proper_instantiation() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+
+        let version = get_contract_version(&deps.storage).unwrap();
+        assert_eq!(CONTRACT_NAME, version.contract);
+        assert_eq!(CONTRACT_VERSION, version.version);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let config: Config = from_binary(&res).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                admin: Addr::unchecked(ADMIN),
+                mint_policy: MintPolicy::Minter {},
+                fees: FeeConfig::default(),
+                paused: false,
+                max_nesting_depth: DEFAULT_MAX_NESTING_DEPTH,
+                max_bundle_assets: DEFAULT_MAX_BUNDLE_ASSETS,
+                asset_filter: AssetFilter::Allowlist {},
+            }
+        );
+    }
+
+    #[test]
+    fn update_config_and_pause() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+        mint_bundle(deps.as_mut(), TOKEN_ID, ALICE);
+
+        let update = ExecuteMsg::UpdateConfig {
+            mint_policy: None,
+            max_nesting_depth: Some(3),
+            max_bundle_assets: None,
+        };
+
+        // only the admin can update
+        let info = mock_info(MINTER, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, update.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        let info = mock_info(ADMIN, &[]);
+        execute(deps.as_mut(), mock_env(), info, update).unwrap();
+        let config = CONFIG.load(&deps.storage).unwrap();
+        assert_eq!(3, config.max_nesting_depth);
+
+        // limits cannot be 0
+        for (max_nesting_depth, max_bundle_assets) in [(Some(0), None), (None, Some(0))] {
+            let update = ExecuteMsg::UpdateConfig {
+                mint_policy: None,
+                max_nesting_depth,
+                max_bundle_assets,
+            };
+            let err =
+                execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), update).unwrap_err();
+            assert!(matches!(err, ContractError::InvalidConfig { .. }));
+        }
+        let config = CONFIG.load(&deps.storage).unwrap();
+        assert_eq!(3, config.max_nesting_depth);
+        assert_eq!(DEFAULT_MAX_BUNDLE_ASSETS, config.max_bundle_assets);
+
+        // only the admin or a pauser can pause
+        let info = mock_info(ALICE, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Pause {}).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let info = mock_info(ADMIN, &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Pause {}).unwrap();
+        assert_eq!(
+            res.events,
+            vec![Event::new("pause").add_attribute("paused", "true")]
+        );
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Paused {}).unwrap();
+        let res: PausedResponse = from_binary(&res).unwrap();
+        assert!(res.paused);
+
+        // deposits and mints are refused while paused
+        let info = mock_info(ALICE, &coins(10, "uatom"));
+        let err = deposit_native(deps.as_mut(), info, TOKEN_ID.into()).unwrap_err();
+        assert_eq!(err, ContractError::Paused {});
+        let msg = Cw20ReceiveMsg {
+            sender: ALICE.into(),
+            amount: Uint128::from(2u128),
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+        };
+        let err = receive_cw20(deps.as_mut(), mock_info(CONTRACT, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Paused {});
+        let msg = Cw721ReceiveMsg {
+            sender: ALICE.into(),
+            token_id: "nft".into(),
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+        };
+        let info = mock_info("nft_contract", &[]);
+        let err = receive_cw721(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(err, ContractError::Paused {});
+        let msg = Cw1155BatchReceiveMsg {
+            operator: ALICE.into(),
+            from: Some(ALICE.into()),
+            batch: vec![("token".into(), Uint128::from(1u128))],
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+        };
+        let info = mock_info("cw1155_contract", &[]);
+        let err = receive_cw1155(deps.as_mut(), info, msg).unwrap_err();
+        assert_eq!(err, ContractError::Paused {});
+        let mint_msg = ExecuteMsg::Mint(MintMsg {
+            base: Cw721MintMsg {
+                token_id: "b".into(),
+                owner: ALICE.into(),
+                extension: None,
+                token_uri: None,
+            },
+        });
+        let info = mock_info(MINTER, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, mint_msg).unwrap_err();
+        assert_eq!(err, ContractError::Paused {});
+
+        // withdrawals keep working
+        let info = mock_info(ALICE, &[]);
+        withdraw(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            TOKEN_ID.into(),
+            WithdrawOptions::default(),
+        )
+        .unwrap();
+
+        let info = mock_info(ADMIN, &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Unpause {}).unwrap();
+        assert_eq!(
+            res.events,
+            vec![Event::new("unpause").add_attribute("paused", "false")]
+        );
+        let config = CONFIG.load(&deps.storage).unwrap();
+        assert!(!config.paused);
+        // the rest of the config is kept
+        assert_eq!(3, config.max_nesting_depth);
+    }
+
+    #[test]
+    fn transfer_admin_and_minter() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+
+        let pending = |deps: Deps| {
+            let res = query(deps, mock_env(), QueryMsg::PendingTransfers {}).unwrap();
+            let res: PendingTransfersResponse = from_binary(&res).unwrap();
+            res
+        };
+
+        // only the admin can propose a new admin
+        let msg = ExecuteMsg::ProposeNewAdmin {
+            admin: ALICE.into(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+        assert_eq!(pending(deps.as_ref()).admin, Some(Addr::unchecked(ALICE)));
+
+        // the proposal can be cancelled
+        let msg = ExecuteMsg::CancelAdminTransfer {};
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            msg.clone(),
+        )
+        .unwrap();
+        let err = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::NoPendingTransfer {});
+        let msg = ExecuteMsg::AcceptAdmin {};
+        let err = execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::NoPendingTransfer {});
+
+        // only the proposed admin can accept
+        let msg = ExecuteMsg::ProposeNewAdmin {
+            admin: ALICE.into(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+        let msg = ExecuteMsg::AcceptAdmin {};
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), msg).unwrap();
+        assert_eq!(
+            CONFIG.load(&deps.storage).unwrap().admin,
+            Addr::unchecked(ALICE)
+        );
+        assert_eq!(pending(deps.as_ref()).admin, None);
+
+        // the new admin can rotate the minter
+        let msg = ExecuteMsg::ProposeNewMinter {
+            minter: "new_minter".into(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), msg).unwrap();
+        assert_eq!(
+            pending(deps.as_ref()).minter,
+            Some(Addr::unchecked("new_minter"))
+        );
+        let msg = ExecuteMsg::AcceptMinter {};
+        execute(deps.as_mut(), mock_env(), mock_info("new_minter", &[]), msg).unwrap();
+
+        let res: MinterResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Minter {}).unwrap()).unwrap();
+        assert_eq!(res.minter, "new_minter");
+        assert_eq!(pending(deps.as_ref()).minter, None);
+
+        // the old minter can no longer mint
+        let mint_msg = ExecuteMsg::Mint(MintMsg {
+            base: Cw721MintMsg {
+                token_id: TOKEN_ID.into(),
+                owner: ALICE.into(),
+                extension: None,
+                token_uri: None,
+            },
+        });
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            mint_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("new_minter", &[]),
+            mint_msg,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn grant_and_revoke_roles() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+
+        let grant = |role: Role, address: &str| ExecuteMsg::GrantRole {
+            role,
+            address: address.into(),
+        };
+        let mint_msg = |token_id: &str| {
+            ExecuteMsg::Mint(MintMsg {
+                base: Cw721MintMsg {
+                    token_id: token_id.into(),
+                    owner: ALICE.into(),
+                    extension: None,
+                    token_uri: None,
+                },
+            })
+        };
+        let update_fees = ExecuteMsg::UpdateFees {
+            treasury: None,
+            mint_price: None,
+            withdraw_fee_bps: 0,
+        };
+
+        // only the admin can grant roles
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            grant(Role::Minter, "hot_minter"),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        for (role, address) in [
+            (Role::Minter, "hot_minter"),
+            (Role::FeeManager, "fee_manager"),
+            (Role::Pauser, "pauser"),
+            (Role::AllowlistManager, "allowlist_manager"),
+        ] {
+            let info = mock_info(ADMIN, &[]);
+            execute(deps.as_mut(), mock_env(), info, grant(role, address)).unwrap();
+        }
+        let msg = QueryMsg::RoleMembers {
+            role: Role::Minter,
+            start_after: None,
+            limit: None,
+        };
+        let res: RoleMembersResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.members, vec!["hot_minter"]);
+
+        // each role only opens its own path
+        let info = mock_info("hot_minter", &[]);
+        execute(deps.as_mut(), mock_env(), info.clone(), mint_msg("a")).unwrap();
+        let err = execute(deps.as_mut(), mock_env(), info, update_fees.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        let info = mock_info("fee_manager", &[]);
+        execute(deps.as_mut(), mock_env(), info.clone(), update_fees).unwrap();
+        let err = execute(deps.as_mut(), mock_env(), info, mint_msg("b")).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        let info = mock_info("allowlist_manager", &[]);
+        let msg = ExecuteMsg::UpdateAssetList {
+            filter: Some(AssetFilter::Denylist {}),
+            add: vec![],
+            remove: vec![],
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("pauser", &[]);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            ExecuteMsg::Pause {},
+        )
+        .unwrap();
+        assert!(CONFIG.load(&deps.storage).unwrap().paused);
+        let update = ExecuteMsg::UpdateConfig {
+            mint_policy: None,
+            max_nesting_depth: Some(1),
+            max_bundle_assets: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), update).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Unpause {}).unwrap();
+
+        // a revoked minter can no longer mint
+        let msg = ExecuteMsg::RevokeRole {
+            role: Role::Minter,
+            address: "hot_minter".into(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+        let info = mock_info("hot_minter", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, mint_msg("b")).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
+
+    #[test]
+    fn try_fees() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+        const TREASURY: &str = "treasury_address";
+
+        let update =
+            |treasury: Option<&str>, mint_price: Option<Price>, bps: u16| ExecuteMsg::UpdateFees {
+                treasury: treasury.map(String::from),
+                mint_price,
+                withdraw_fee_bps: bps,
+            };
+        let info = mock_info(ADMIN, &[]);
+
+        let msg = update(Some(TREASURY), None, 100);
+        let err = execute(deps.as_mut(), mock_env(), mock_info(MINTER, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let msg = update(None, None, 100);
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidFees { .. }));
+        let msg = update(Some(TREASURY), None, MAX_WITHDRAW_FEE_BPS + 1);
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidFees { .. }));
+
+        // funds are refused while there is no native price to pay
+        let mint_msg = |token_id: &str| {
+            ExecuteMsg::Mint(MintMsg {
+                base: Cw721MintMsg {
+                    token_id: token_id.into(),
+                    owner: ALICE.into(),
+                    extension: None,
+                    token_uri: None,
+                },
+            })
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &coins(10, "uatom")),
+            mint_msg("free"),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::UnexpectedFunds {});
+
+        let price = Price::Native(coin(10, "uatom"));
+        let msg = update(Some(TREASURY), Some(price), 100);
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Fees {}).unwrap();
+        let fees: FeeConfig = from_binary(&res).unwrap();
+        assert_eq!(
+            fees,
+            FeeConfig {
+                treasury: Some(Addr::unchecked(TREASURY)),
+                mint_price: Some(MintPrice::Native(coin(10, "uatom"))),
+                withdraw_fee_bps: 100,
+            }
+        );
+
+        // the mint price has to be attached
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            mint_msg(TOKEN_ID),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::MintFeeRequired {
+                fee: "10uatom".into()
+            }
+        );
+        let info = mock_info(MINTER, &coins(10, "uatom"));
+        let res = execute(deps.as_mut(), mock_env(), info, mint_msg(TOKEN_ID)).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: TREASURY.into(),
+                amount: coins(10, "uatom"),
+            })]
+        );
+        assert!(res.attributes.contains(&attr("mint_fee", "10uatom")));
+
+        // a hook sender cannot be charged, so bundles with a price are not created by deposit
+        let public = ExecuteMsg::UpdateConfig {
+            mint_policy: Some(MintPolicy::Public {
+                max_per_owner: None,
+            }),
+            max_nesting_depth: None,
+            max_bundle_assets: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), public).unwrap();
+        let deposit_msg = DepositMsg::CreateBundle {
+            bundle_id: "b".into(),
+            owner: None,
+            token_uri: None,
+            extension: None,
+        };
+        let msg = Cw721ReceiveMsg {
+            sender: ALICE.into(),
+            token_id: "nft".into(),
+            msg: to_binary(&deposit_msg).unwrap(),
+        };
+        let info = mock_info(CONTRACT, &[]);
+        let err = receive_cw721(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::MintPriceOnDeposit {});
+        let price = Price::Cw20(Cw20Asset {
+            contract_address: "fee_token".into(),
+            amount: Uint128::from(7u128),
+        });
+        let update_msg = update(Some(TREASURY), Some(price), 100);
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), update_msg).unwrap();
+        let err = receive_cw721(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(err, ContractError::MintPriceOnDeposit {});
+
+        // a cw20 price is taken from the sender's allowance, and funds are refused
+        let create = ExecuteMsg::CreateBundle {
+            token_uri: None,
+            extension: None,
+        };
+        let info = mock_info(ALICE, &coins(10, "uatom"));
+        let err = execute(deps.as_mut(), mock_env(), info, create.clone()).unwrap_err();
+        assert_eq!(err, ContractError::UnexpectedFunds {});
+        let info = mock_info(ALICE, &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, create).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(WasmMsg::Execute {
+                contract_addr: "fee_token".into(),
+                msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                    owner: ALICE.into(),
+                    recipient: TREASURY.into(),
+                    amount: Uint128::from(7u128),
+                })
+                .unwrap(),
+                funds: vec![],
+            })]
+        );
+        assert!(res.attributes.contains(&attr("mint_fee", "7fee_token")));
+
+        // 1% of fungible amounts goes to the treasury on withdraw
+        let info = mock_info(ALICE, &coins(1000, "uatom"));
+        deposit_native(deps.as_mut(), info, TOKEN_ID.into()).unwrap();
+        add_cw20(
+            &mut deps.storage,
+            TOKEN_ID,
+            &Addr::unchecked(CONTRACT),
+            Uint128::from(500u128),
+        )
+        .unwrap();
+        let info = mock_info(ALICE, &[]);
+        let res = withdraw(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            TOKEN_ID.into(),
+            WithdrawOptions::default(),
+        )
+        .unwrap();
+        let cw20_transfer = |recipient: &str, amount: u128| {
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: CONTRACT.into(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: recipient.into(),
+                    amount: Uint128::from(amount),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        };
+        assert_eq!(
+            res.messages,
+            vec![
+                cw20_transfer(ALICE, 495),
+                SubMsg::new(BankMsg::Send {
+                    to_address: ALICE.into(),
+                    amount: coins(990, "uatom"),
+                }),
+                cw20_transfer(TREASURY, 5),
+                SubMsg::new(BankMsg::Send {
+                    to_address: TREASURY.into(),
+                    amount: coins(10, "uatom"),
+                }),
+            ]
+        );
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "withdraw"),
+                attr("recipient", ALICE),
+                attr("withdraw_fee", "5contract_address"),
+                attr("withdraw_fee", "10uatom"),
+            ]
+        );
+        // events record the full amounts leaving the bundle, before the fee
+        let withdraw_event = |asset_type: &str, contract: &str, amount: &str| {
+            Event::new("bundle_withdraw")
+                .add_attribute("bundle_id", TOKEN_ID)
+                .add_attribute("asset_type", asset_type)
+                .add_attribute("contract", contract)
+                .add_attribute("token_id", "")
+                .add_attribute("amount", amount)
+        };
+        assert_eq!(
+            res.events,
+            vec![
+                withdraw_event("cw20", CONTRACT, "500"),
+                withdraw_event("native", "uatom", "1000"),
+            ]
+        );
+
+        // cw1155 balances are charged the same way
+        let msg = Cw1155BatchReceiveMsg {
+            operator: ALICE.into(),
+            from: Some(ALICE.into()),
+            batch: vec![("gold".into(), Uint128::from(300u128))],
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+        };
+        receive_cw1155(deps.as_mut(), mock_info("cw1155_contract", &[]), msg).unwrap();
+        let env = mock_env();
+        let res = withdraw_assets(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ALICE, &[]),
+            TOKEN_ID.into(),
+            BundleAssets {
+                cw1155: vec![Cw1155Asset {
+                    contract_address: "cw1155_contract".into(),
+                    token_id: "gold".into(),
+                    amount: Uint128::from(200u128),
+                }],
+                ..BundleAssets::default()
+            },
+            false,
+        )
+        .unwrap();
+        let cw1155_send = |recipient: &str, amount: u128| {
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: "cw1155_contract".into(),
+                msg: to_binary(&Cw1155ExecuteMsg::SendFrom {
+                    from: env.contract.address.to_string(),
+                    to: recipient.into(),
+                    token_id: "gold".into(),
+                    value: Uint128::from(amount),
+                    msg: None,
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        };
+        assert_eq!(
+            res.messages,
+            vec![cw1155_send(ALICE, 198), cw1155_send(TREASURY, 2)]
+        );
+        assert!(res
+            .attributes
+            .contains(&attr("withdraw_fee", "2cw1155_contract/gold")));
+
+        // and so are whole bundle withdrawals
+        let res = withdraw(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ALICE, &[]),
+            TOKEN_ID.into(),
+            WithdrawOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(WasmMsg::Execute {
+                    contract_addr: "cw1155_contract".into(),
+                    msg: to_binary(&Cw1155ExecuteMsg::BatchSendFrom {
+                        from: env.contract.address.to_string(),
+                        to: ALICE.into(),
+                        batch: vec![("gold".into(), Uint128::from(99u128))],
+                        msg: None,
+                    })
+                    .unwrap(),
+                    funds: vec![],
+                }),
+                cw1155_send(TREASURY, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn migrate_versions() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+
+        // 0.1.0 instances were recorded under the cw721-base name and had no config
+        set_contract_version(&mut deps.storage, LEGACY_CONTRACT_NAME, "0.9.2").unwrap();
+        CONFIG.remove(&mut deps.storage);
+        // and did not count bundle assets
+        let asset = CW721Wrapper {
+            contract_address: Addr::unchecked(CONTRACT),
+            token_id: "nft".into(),
+        };
+        cw721_bundle()
+            .save(
+                &mut deps.storage,
+                ("a", &asset.contract_address, "nft"),
+                &asset,
+            )
+            .unwrap();
+        NATIVE_BUNDLE
+            .save(&mut deps.storage, ("a", "uatom"), &Uint128::from(5u128))
+            .unwrap();
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "migrate"),
+                attr("from_version", "0.1.0"),
+                attr("to_version", CONTRACT_VERSION),
+            ]
+        );
+        let version = get_contract_version(&deps.storage).unwrap();
+        assert_eq!(CONTRACT_NAME, version.contract);
+        assert_eq!(CONTRACT_VERSION, version.version);
+        let config = CONFIG.load(&deps.storage).unwrap();
+        assert_eq!(config.admin, Addr::unchecked(MINTER));
+        assert_eq!(2, ASSET_COUNTS.load(&deps.storage, "a").unwrap());
+
+        // migrating to the same version is allowed
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+        set_contract_version(&mut deps.storage, CONTRACT_NAME, "99.0.0").unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::CannotDowngrade {
+                from: "99.0.0".into(),
+                to: CONTRACT_VERSION.into(),
+            }
+        );
+
+        set_contract_version(&mut deps.storage, "crates.io:cw20-base", "0.1.0").unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidMigrationContract {
+                contract: "crates.io:cw20-base".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn mint() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+
+        let info = mock_info(MINTER, &[]);
+
+        let mint_msg = cw721_execute_msg::Mint(Cw721MintMsg {
+            token_id: TOKEN_ID.into(),
+            owner: ALICE.into(),
+            extension: None,
+            token_uri: Some("ipfs://QmVKZ5YZYDqdnAQo93kaYbcMtzGgx9kvpAVwoERm5mZezh".to_string()),
+        });
+        let _ = Cw721Contract::<Extension, Empty>::default().execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            mint_msg,
+        );
+
+        // ensure num tokens increases
+        let count = Cw721Contract::<Extension, Empty>::default()
+            .query(deps.as_ref(), mock_env(), cw721_query_msg::NumTokens {})
+            .unwrap();
+        let response: NumTokensResponse = from_binary(&count).unwrap();
+        assert_eq!(1, response.count);
+    }
+
+    #[test]
+    fn try_receive_cw20() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+
+        let info = mock_info(MINTER, &[]);
+        let mint_msg = cw721_execute_msg::Mint(Cw721MintMsg {
+            token_id: TOKEN_ID.into(),
+            owner: ALICE.into(),
+            extension: None,
+            token_uri: Some("ipfs://QmVKZ5YZYDqdnAQo93kaYbcMtzGgx9kvpAVwoERm5mZezh".to_string()),
+        });
+        let _ = Cw721Contract::<Extension, Empty>::default().execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            mint_msg,
+        );
+
+        let info = mock_info(CONTRACT, &[]);
+        let msg = Cw20ReceiveMsg {
+            sender: ALICE.into(),
+            amount: Uint128::from(2u128),
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+        };
+
+        // receive cw20 tokens
+        let res = receive_cw20(deps.as_mut(), info, msg).unwrap();
+        let info = mock_info(CONTRACT, &[]);
+        let msg = Cw20ReceiveMsg {
+            sender: ALICE.into(),
+            amount: Uint128::from(2u128),
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+        };
+        assert_eq!(
+            res,
+            Response::default()
+                .add_event(
+                    Event::new("bundle_deposit")
+                        .add_attribute("bundle_id", "a")
+                        .add_attribute("asset_type", "cw20")
+                        .add_attribute("contract", info.sender.as_str())
+                        .add_attribute("token_id", "")
+                        .add_attribute("amount", "2")
+                )
+                .add_attribute("action", "deposit_cw20")
+                .add_attribute("sender", msg.sender.clone())
+                .add_attribute("contract_sender", info.sender.to_string())
+                .add_attribute("amount", "2")
+                .add_attribute("bundle_id", "a")
+        );
+
+        // ensure num tokens in bundle is 1
+        let bundle = load_bundle_contents(&deps.storage, "a").unwrap();
+        assert_eq!(1, bundle.cw20.len());
+
+        // the hook as sent by cw20 contracts adds to the same entry
+        let wire = msg.into_binary().unwrap();
+        let msg: ExecuteMsg = from_binary(&wire).unwrap();
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let bundle = load_bundle_contents(&deps.storage, "a").unwrap();
+        assert_eq!(Uint128::from(4u128), bundle.cw20[0].amount);
+    }
+
+    #[test]
+    fn try_receive_cw721() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+
+        let info = mock_info(MINTER, &[]);
+        let mint_msg = cw721_execute_msg::Mint(Cw721MintMsg {
+            token_id: TOKEN_ID.into(),
+            owner: ALICE.into(),
+            extension: None,
+            token_uri: Some("ipfs://QmVKZ5YZYDqdnAQo93kaYbcMtzGgx9kvpAVwoERm5mZezh".to_string()),
+        });
+        let _ = Cw721Contract::<Extension, Empty>::default().execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            mint_msg,
+        );
+
+        let info = mock_info(CONTRACT, &[]);
+        let msg = Cw721ReceiveMsg {
+            sender: ALICE.into(),
+            token_id: TOKEN_ID.into(),
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+        };
+
+        // receive cw721 token
+        let res = receive_cw721(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info(CONTRACT, &[]);
+        let msg = Cw721ReceiveMsg {
+            sender: ALICE.into(),
+            token_id: TOKEN_ID.into(),
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+        };
+        assert_eq!(
+            res,
+            Response::default()
+                .add_event(
+                    Event::new("bundle_deposit")
+                        .add_attribute("bundle_id", "a")
+                        .add_attribute("asset_type", "cw721")
+                        .add_attribute("contract", info.sender.as_str())
+                        .add_attribute("token_id", "a")
+                        .add_attribute("amount", "1")
+                )
+                .add_attribute("action", "deposit_cw721")
+                .add_attribute("sender", msg.sender)
+                .add_attribute("contract_sender", info.sender.to_string())
+                .add_attribute("token_id", msg.token_id)
+                .add_attribute("bundle_id", "a")
+        );
+
+        // ensure num tokens in bundle is 1
+        let bundle = load_bundle_contents(&deps.storage, "a").unwrap();
+        assert_eq!(1, bundle.cw721.len());
+    }
+
+    #[test]
+    fn try_receive_cw1155() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+
+        let info = mock_info(MINTER, &[]);
+        let mint_msg = cw721_execute_msg::Mint(Cw721MintMsg {
+            token_id: TOKEN_ID.into(),
+            owner: ALICE.into(),
+            extension: None,
+            token_uri: Some("ipfs://QmVKZ5YZYDqdnAQo93kaYbcMtzGgx9kvpAVwoERm5mZezh".to_string()),
+        });
+        let _ = Cw721Contract::<Extension, Empty>::default().execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            mint_msg,
+        );
+
+        let info = mock_info(CONTRACT, &[]);
+        let msg = Cw1155BatchReceiveMsg {
+            operator: ALICE.into(),
+            from: None,
+            batch: vec![(TOKEN_ID.into(), Uint128::from(2u128))],
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+        };
+
+        // receive cw721 token
+        let res = receive_cw1155(deps.as_mut(), info, msg).unwrap();
+        let info = mock_info(CONTRACT, &[]);
+        let msg = Cw1155BatchReceiveMsg {
+            operator: ALICE.into(),
+            from: None,
+            batch: vec![(TOKEN_ID.into(), Uint128::from(2u128))],
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+        };
+        assert_eq!(
+            res,
+            Response::default()
+                .add_event(
+                    Event::new("bundle_deposit")
+                        .add_attribute("bundle_id", "a")
+                        .add_attribute("asset_type", "cw1155")
+                        .add_attribute("contract", info.sender.as_str())
+                        .add_attribute("token_id", "a")
+                        .add_attribute("amount", "2")
+                )
+                .add_attribute("action", "deposit_cw1155")
+                .add_attribute("sender", msg.operator)
+                .add_attribute("contract_sender", info.sender.to_string())
+                .add_attribute("bundle_id", "a")
+        );
+
+        // ensure num tokens in bundle is 1
+        let bundle = load_bundle_contents(&deps.storage, "a").unwrap();
+        assert_eq!(1, bundle.cw1155.len());
+
+        // the single token hook, as sent by cw1155 contracts, and further batches add to
+        // the same entry
+        let wire = Cw1155ReceiveMsg {
+            operator: ALICE.into(),
+            from: Some(ALICE.into()),
+            token_id: TOKEN_ID.into(),
+            amount: Uint128::from(3u128),
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+        }
+        .into_binary()
+        .unwrap();
+        let msg: ExecuteMsg = from_binary(&wire).unwrap();
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = Cw1155BatchReceiveMsg {
+            operator: ALICE.into(),
+            from: None,
+            batch: vec![(TOKEN_ID.into(), Uint128::from(1u128))],
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+        };
+        receive_cw1155(deps.as_mut(), info, msg).unwrap();
+        let bundle = load_bundle_contents(&deps.storage, "a").unwrap();
+        assert_eq!(1, bundle.cw1155.len());
+        assert_eq!(Uint128::from(6u128), bundle.cw1155[0].amount);
+
+        // only the owner can deposit
+        let msg = Cw1155ReceiveMsg {
+            operator: MINTER.into(),
+            from: Some(MINTER.into()),
+            token_id: TOKEN_ID.into(),
+            amount: Uint128::from(3u128),
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+        };
+        let info = mock_info(CONTRACT, &[]);
+        let err = receive_cw1155_single(deps.as_mut(), info, msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
+
+    #[test]
+    fn try_receive_create_bundle() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+
+        // a contract wraps an nft into a new bundle for alice
+        let deposit_msg = |bundle_id: &str| DepositMsg::CreateBundle {
+            bundle_id: bundle_id.into(),
+            owner: Some(ALICE.into()),
+            token_uri: None,
+            extension: None,
+        };
+        let info = mock_info(CONTRACT, &[]);
+        let msg = Cw721ReceiveMsg {
+            sender: "wrapper_address".into(),
+            token_id: "nft".into(),
+            msg: to_binary(&deposit_msg("b")).unwrap(),
+        };
+
+        // only public minting allows it, even for a sender claiming to be the minter
+        let forged = Cw721ReceiveMsg {
+            sender: MINTER.into(),
+            ..msg.clone()
+        };
+        let err = receive_cw721(deps.as_mut(), mock_env(), info.clone(), forged).unwrap_err();
+        assert_eq!(err, ContractError::PublicMintDisabled {});
+        let update = |max_per_owner: Option<u32>| ExecuteMsg::UpdateConfig {
+            mint_policy: Some(MintPolicy::Public { max_per_owner }),
+            max_nesting_depth: None,
+            max_bundle_assets: None,
+        };
+        let admin = mock_info(ADMIN, &[]);
+        execute(deps.as_mut(), mock_env(), admin.clone(), update(Some(1))).unwrap();
+
+        // a capped hook creation needs assets of allowlisted contracts
+        let asset_filter = |filter: AssetFilter| ExecuteMsg::UpdateAssetList {
+            filter: Some(filter),
+            add: vec![],
+            remove: vec![],
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            admin.clone(),
+            asset_filter(AssetFilter::Off {}),
+        )
+        .unwrap();
+        let err = receive_cw721(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::CappedDepositNotAllowlisted {});
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            admin.clone(),
+            asset_filter(AssetFilter::Allowlist {}),
+        )
+        .unwrap();
+
+        let res = receive_cw721(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+        assert_eq!(res.attributes[4], attr("bundle_id", "b"),);
+
+        let cw721_contract = Cw721Contract::<Extension, Empty>::default();
+        let token_info = cw721_contract.tokens.load(&deps.storage, "b").unwrap();
+        assert_eq!(token_info.owner, Addr::unchecked(ALICE));
+        assert_eq!(1, cw721_contract.token_count(&deps.storage).unwrap());
+        let bundle = load_bundle_contents(&deps.storage, "b").unwrap();
+        assert_eq!(
+            bundle.cw721,
+            vec![CW721Wrapper {
+                contract_address: Addr::unchecked(CONTRACT),
+                token_id: "nft".into(),
+            }]
+        );
+
+        // the per owner cap counts bundles created by deposit against their owner, so
+        // rotating forged senders does not get around it
+        let other = Cw721ReceiveMsg {
+            sender: "other_wrapper_address".into(),
+            token_id: "other_nft".into(),
+            msg: to_binary(&deposit_msg("c")).unwrap(),
+        };
+        let err = receive_cw721(deps.as_mut(), mock_env(), info.clone(), other).unwrap_err();
+        assert_eq!(err, ContractError::MintLimitReached { max: 1 });
+        assert_eq!(
+            Some(1),
+            CREATED_BUNDLES
+                .may_load(&deps.storage, &Addr::unchecked(ALICE))
+                .unwrap()
+        );
+
+        // bundle ids cannot be taken twice
+        execute(deps.as_mut(), mock_env(), admin, update(None)).unwrap();
+        let err = receive_cw721(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(err, ContractError::Claimed {});
+    }
+
+    #[test]
+    fn try_deposit_from_approvals() {
+        let mut deps = dependencies_with_owners(vec![("cw721_contract", "nft", ALICE.into())]);
+        setup_contract(deps.as_mut());
+        mint_bundle(deps.as_mut(), TOKEN_ID, ALICE);
+
+        let cw20 = vec![Cw20Asset {
+            contract_address: "cw20_contract".into(),
+            amount: Uint128::from(10u128),
+        }];
+        let cw721 = vec![Cw721Asset {
+            contract_address: "cw721_contract".into(),
+            token_id: "nft".into(),
+        }];
+        let cw1155 = vec![Cw1155Asset {
+            contract_address: "cw1155_contract".into(),
+            token_id: "gold".into(),
+            amount: Uint128::from(5u128),
+        }];
+        let deposit = ExecuteMsg::DepositFromApprovals {
+            bundle_id: TOKEN_ID.into(),
+            cw20,
+            cw721,
+            cw1155,
+        };
+
+        // only the owner can deposit
+        let info = mock_info(MINTER, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, deposit.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        let info = mock_info(ALICE, &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, deposit).unwrap();
+        let contract = mock_env().contract.address.to_string();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::reply_on_success(
+                    WasmMsg::Execute {
+                        contract_addr: "cw721_contract".into(),
+                        msg: to_binary(&Cw721ExecuteMsg::TransferNft {
+                            recipient: contract.clone(),
+                            token_id: "nft".into(),
+                        })
+                        .unwrap(),
+                        funds: vec![],
+                    },
+                    1
+                ),
+                SubMsg::reply_on_success(
+                    WasmMsg::Execute {
+                        contract_addr: "cw20_contract".into(),
+                        msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                            owner: ALICE.into(),
+                            recipient: contract.clone(),
+                            amount: Uint128::from(10u128),
+                        })
+                        .unwrap(),
+                        funds: vec![],
+                    },
+                    2
+                ),
+                SubMsg::reply_on_success(
+                    WasmMsg::Execute {
+                        contract_addr: "cw1155_contract".into(),
+                        msg: to_binary(&Cw1155ExecuteMsg::SendFrom {
+                            from: ALICE.into(),
+                            to: contract,
+                            token_id: "gold".into(),
+                            value: Uint128::from(5u128),
+                            msg: None,
+                        })
+                        .unwrap(),
+                        funds: vec![],
+                    },
+                    3
+                ),
+            ]
+        );
+
+        // nothing is recorded before the transfers reply
+        let bundle = load_bundle_contents(&deps.storage, TOKEN_ID).unwrap();
+        assert!(bundle.cw20.is_empty() && bundle.cw721.is_empty() && bundle.cw1155.is_empty());
+
+        for id in 1..=3 {
+            let msg = Reply {
+                id,
+                result: ContractResult::Ok(SubMsgExecutionResponse {
+                    events: vec![],
+                    data: None,
+                }),
+            };
+            reply(deps.as_mut(), mock_env(), msg).unwrap();
+        }
+        let bundle = load_bundle_contents(&deps.storage, TOKEN_ID).unwrap();
+        assert_eq!(1, bundle.cw20.len());
+        assert_eq!(1, bundle.cw721.len());
+        assert_eq!(Uint128::from(5u128), bundle.cw1155[0].amount);
+        assert_eq!(
+            0,
+            PENDING_DEPOSITS
+                .range(&deps.storage, None, None, Order::Ascending)
+                .count()
+        );
+    }
+
+    #[test]
+    fn deposit_from_approvals_only_pulls_owned_tokens() {
+        let contract = mock_env().contract.address.to_string();
+        let mut deps = dependencies_with_owners(vec![
+            ("cw721_contract", "nft", contract.clone()),
+            ("cw721_contract", "other", ALICE.into()),
+        ]);
+        setup_contract(deps.as_mut());
+        for bundle_id in ["a", "b", "c", "d"] {
+            mint_bundle(deps.as_mut(), bundle_id, ALICE);
+        }
+        mint_bundle(deps.as_mut(), "bob", "bob_address");
+
+        // an nft deposited into a and bundle b nested in a, both held by the contract
+        let msg = Cw721ReceiveMsg {
+            sender: ALICE.into(),
+            token_id: "nft".into(),
+            msg: to_binary(&DepositCwMsg {
+                bundle_id: "a".into(),
+            })
+            .unwrap(),
+        };
+        receive_cw721(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("cw721_contract", &[]),
+            msg,
+        )
+        .unwrap();
+        let msg = Cw721ReceiveMsg {
+            sender: ALICE.into(),
+            token_id: "b".into(),
+            msg: to_binary(&DepositCwMsg {
+                bundle_id: "a".into(),
+            })
+            .unwrap(),
+        };
+        receive_cw721(deps.as_mut(), mock_env(), mock_info(&contract, &[]), msg).unwrap();
+
+        let pull = |contract_address: &str, token_id: &str| ExecuteMsg::DepositFromApprovals {
+            bundle_id: "bob".into(),
+            cw20: vec![],
+            cw721: vec![Cw721Asset {
+                contract_address: contract_address.into(),
+                token_id: token_id.into(),
+            }],
+            cw1155: vec![],
+        };
+        let info = mock_info("bob_address", &[]);
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            pull("cw721_contract", "nft"),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::AssetAlreadyBundled {});
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            pull(&contract, "b"),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::AssetAlreadyBundled {});
+
+        // tokens and bundles of other owners cannot be pulled either
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            pull("cw721_contract", "other"),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(deps.as_mut(), mock_env(), info, pull(&contract, "d")).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // while the owner can pull its own bundle in
+        let info = mock_info(ALICE, &[]);
+        let msg = ExecuteMsg::DepositFromApprovals {
+            bundle_id: "c".into(),
+            cw20: vec![],
+            cw721: vec![Cw721Asset {
+                contract_address: contract,
+                token_id: "d".into(),
+            }],
+            cw1155: vec![],
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(1, res.messages.len());
+    }
+
+    #[test]
+    fn try_nest_bundles() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+        for bundle_id in ["a", "b", "c", "d", "e"] {
+            mint_bundle(deps.as_mut(), bundle_id, ALICE);
+        }
+        let info = mock_info(ADMIN, &[]);
+        let update = ExecuteMsg::UpdateConfig {
+            mint_policy: None,
+            max_nesting_depth: Some(2),
+            max_bundle_assets: None,
+        };
+        execute(deps.as_mut(), mock_env(), info, update).unwrap();
+
+        // bundles of this contract sent into another bundle
+        let nest = |deps: DepsMut, child: &str, parent: &str| {
+            let msg = Cw721ReceiveMsg {
+                sender: ALICE.into(),
+                token_id: child.into(),
+                msg: to_binary(&DepositCwMsg {
+                    bundle_id: parent.into(),
+                })
+                .unwrap(),
+            };
+            let info = mock_info(mock_env().contract.address.as_str(), &[]);
+            receive_cw721(deps, mock_env(), info, msg)
+        };
+
+        nest(deps.as_mut(), "b", "a").unwrap();
+        nest(deps.as_mut(), "c", "b").unwrap();
+
+        let err = nest(deps.as_mut(), "d", "d").unwrap_err();
+        assert_eq!(err, ContractError::NestingCycle {});
+        let err = nest(deps.as_mut(), "a", "c").unwrap_err();
+        assert_eq!(err, ContractError::NestingCycle {});
+
+        // c is already two levels below a
+        let err = nest(deps.as_mut(), "d", "c").unwrap_err();
+        assert_eq!(err, ContractError::NestingTooDeep { max: 2 });
+        // a has two levels below it
+        let err = nest(deps.as_mut(), "a", "e").unwrap_err();
+        assert_eq!(err, ContractError::NestingTooDeep { max: 2 });
+
+        let msg = QueryMsg::BundleContents {
+            bundle_id: "a".into(),
+            recursive: Some(true),
+        };
+        let res: BundleContentsResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(1, res.cw721.len());
+        assert_eq!(1, res.nested.len());
+        assert_eq!("b", res.nested[0].bundle_id);
+        assert_eq!(1, res.nested[0].contents.nested.len());
+        assert_eq!("c", res.nested[0].contents.nested[0].bundle_id);
+        assert!(res.nested[0].contents.nested[0].contents.nested.is_empty());
+
+        let msg = QueryMsg::BundleContents {
+            bundle_id: "a".into(),
+            recursive: None,
+        };
+        let res: BundleContentsResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert!(res.nested.is_empty());
+
+        // a bundle recorded under two parents has no single chain to check
+        let contract = mock_env().contract.address;
+        let asset = CW721Wrapper {
+            contract_address: contract.clone(),
+            token_id: "b".into(),
+        };
+        cw721_bundle()
+            .save(&mut deps.storage, ("e", &contract, "b"), &asset)
+            .unwrap();
+        let err = nest(deps.as_mut(), "d", "b").unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::Std(StdError::GenericErr { .. })
+        ));
+    }
+
+    #[test]
+    fn try_create_bundle() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+
+        let create = ExecuteMsg::CreateBundle {
+            token_uri: None,
+            extension: None,
+        };
+        let info = mock_info(ALICE, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), create.clone()).unwrap_err();
+        assert_eq!(err, ContractError::PublicMintDisabled {});
+
+        let update = ExecuteMsg::UpdateConfig {
+            mint_policy: Some(MintPolicy::Public {
+                max_per_owner: Some(2),
+            }),
+            max_nesting_depth: None,
+            max_bundle_assets: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), update).unwrap();
+        // the minter can still mint, its ids are skipped
+        mint_bundle(deps.as_mut(), "2", MINTER);
+
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), create.clone()).unwrap();
+        assert_eq!(
+            res,
+            Response::default()
+                .add_attribute("action", "create_bundle")
+                .add_attribute("owner", ALICE)
+                .add_attribute("bundle_id", "1")
+        );
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), create.clone()).unwrap();
+        assert_eq!(res.attributes[2], attr("bundle_id", "3"));
+        let token_info = Cw721Contract::<Extension, Empty>::default()
+            .tokens
+            .load(&deps.storage, "3")
+            .unwrap();
+        assert_eq!(token_info.owner, Addr::unchecked(ALICE));
+
+        let err = execute(deps.as_mut(), mock_env(), info, create.clone()).unwrap_err();
+        assert_eq!(err, ContractError::MintLimitReached { max: 2 });
+
+        let info = mock_info("bob_address", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, create).unwrap();
+        assert_eq!(res.attributes[2], attr("bundle_id", "4"));
+        let count = Cw721Contract::<Extension, Empty>::default()
+            .token_count(&deps.storage)
+            .unwrap();
+        assert_eq!(4, count);
+    }
+
+    #[test]
+    fn try_receive_split() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+        mint_bundle(deps.as_mut(), "a", ALICE);
+        mint_bundle(deps.as_mut(), "b", ALICE);
+        mint_bundle(deps.as_mut(), "c", MINTER);
+
+        let split = |splits: Vec<(&str, u128)>| {
+            to_binary(&DepositMsg::Split {
+                splits: splits
+                    .into_iter()
+                    .map(|(bundle_id, amount)| BundleSplit {
+                        bundle_id: bundle_id.into(),
+                        amount: Uint128::from(amount),
+                    })
+                    .collect(),
+            })
+            .unwrap()
+        };
+        let cw20_msg = |msg: Binary| Cw20ReceiveMsg {
+            sender: ALICE.into(),
+            amount: Uint128::from(10u128),
+            msg,
+        };
+        let info = mock_info(CONTRACT, &[]);
+
+        // amounts must add up to the amount received
+        let msg = cw20_msg(split(vec![("a", 4), ("b", 4)]));
+        let err = receive_cw20(deps.as_mut(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidSplit {});
+
+        // every bundle must be owned by the sender
+        let msg = cw20_msg(split(vec![("a", 4), ("c", 6)]));
+        let err = receive_cw20(deps.as_mut(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        let msg = cw20_msg(split(vec![("a", 4), ("b", 6)]));
+        let res = receive_cw20(deps.as_mut(), info.clone(), msg).unwrap();
+        assert_eq!(res.attributes[4], attr("bundle_id", "a,b"));
+        let bundle = load_bundle_contents(&deps.storage, "a").unwrap();
+        assert_eq!(Uint128::from(4u128), bundle.cw20[0].amount);
+        let bundle = load_bundle_contents(&deps.storage, "b").unwrap();
+        assert_eq!(Uint128::from(6u128), bundle.cw20[0].amount);
+
+        // a cw1155 split takes a single token
+        let msg = Cw1155BatchReceiveMsg {
+            operator: ALICE.into(),
+            from: None,
+            batch: vec![
+                ("gold".into(), Uint128::from(5u128)),
+                ("silver".into(), Uint128::from(5u128)),
+            ],
+            msg: split(vec![("a", 5), ("b", 5)]),
+        };
+        let err = receive_cw1155(deps.as_mut(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidSplit {});
+        let msg = Cw1155BatchReceiveMsg {
+            operator: ALICE.into(),
+            from: None,
+            batch: vec![("gold".into(), Uint128::from(5u128))],
+            msg: split(vec![("a", 2), ("b", 3)]),
+        };
+        receive_cw1155(deps.as_mut(), info.clone(), msg).unwrap();
+        let bundle = load_bundle_contents(&deps.storage, "b").unwrap();
+        assert_eq!(Uint128::from(3u128), bundle.cw1155[0].amount);
+
+        // nfts cannot be split
+        let msg = Cw721ReceiveMsg {
+            sender: ALICE.into(),
+            token_id: "nft".into(),
+            msg: split(vec![("a", 1)]),
+        };
+        let err = receive_cw721(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidSplit {});
+    }
+
+    #[test]
+    fn try_withdraw() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+
+        let info = mock_info(MINTER, &[]);
+        let mint_msg = cw721_execute_msg::Mint(Cw721MintMsg {
+            token_id: TOKEN_ID.into(),
+            owner: ALICE.into(),
+            extension: None,
+            token_uri: Some("ipfs://QmVKZ5YZYDqdnAQo93kaYbcMtzGgx9kvpAVwoERm5mZezh".to_string()),
+        });
+        let _ = Cw721Contract::<Extension, Empty>::default().execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            mint_msg,
+        );
+
+        let info = mock_info(MINTER, &[]);
+        let msg = Cw721ReceiveMsg {
+            sender: ALICE.into(),
+            token_id: TOKEN_ID.into(),
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+        };
+
+        // receive cw721 token
+        let _res = receive_cw721(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // withdraw
+        let info = mock_info(ALICE, &[]);
+        let _res = withdraw(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            "a".into(),
+            WithdrawOptions::default(),
+        )
+        .unwrap();
+
+        // ensure the bundle entries are removed
+        let bundle = load_bundle_contents(&deps.storage, "a").unwrap();
+        assert!(bundle.cw721.is_empty());
+    }
+
+    #[test]
+    fn try_lock_and_seal() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+        mint_bundle(deps.as_mut(), TOKEN_ID, ALICE);
+
+        let msg = Cw20ReceiveMsg {
+            sender: ALICE.into(),
+            amount: Uint128::from(2u128),
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+        };
+        receive_cw20(deps.as_mut(), mock_info(CONTRACT, &[]), msg).unwrap();
+
+        let height = mock_env().block.height;
+        let lock_msg = |until: u64| ExecuteMsg::Lock {
+            bundle_id: TOKEN_ID.into(),
+            until: Expiration::AtHeight(until),
+        };
+
+        // only the owner can lock, and not in the past
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            lock_msg(height + 10),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            lock_msg(height),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Expired {});
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            lock_msg(height + 10),
+        )
+        .unwrap();
+
+        // an active lock can only be extended
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            lock_msg(height + 5),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::LockNotExtended {});
+
+        let query_msg = QueryMsg::BundleLock {
+            bundle_id: TOKEN_ID.into(),
+        };
+        let res: BundleLockResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), query_msg.clone()).unwrap()).unwrap();
+        assert_eq!(
+            res,
+            BundleLockResponse {
+                lock: Some(BundleLock::Until(Expiration::AtHeight(height + 10))),
+                active: true,
+            }
+        );
+
+        // withdrawals are refused, the bundle can still be transferred
+        let err = withdraw(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            "a".into(),
+            WithdrawOptions::default(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::BundleLocked {});
+        let err = unbundle(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            "a".into(),
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::BundleLocked {});
+        let msg = ExecuteMsg::TransferNft {
+            recipient: MINTER.into(),
+            token_id: TOKEN_ID.into(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), msg).unwrap();
+        let err = withdraw(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            "a".into(),
+            WithdrawOptions::default(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::BundleLocked {});
+
+        // once expired the lock no longer applies
+        let mut env = mock_env();
+        env.block.height = height + 10;
+        let res: BundleLockResponse =
+            from_binary(&query(deps.as_ref(), env.clone(), query_msg.clone()).unwrap()).unwrap();
+        assert!(!res.active);
+        withdraw(
+            deps.as_mut(),
+            env,
+            mock_info(MINTER, &[]),
+            "a".into(),
+            WithdrawOptions::default(),
+        )
+        .unwrap();
+
+        // a sealed bundle refuses withdrawals, deposits and new locks for good
+        let msg = ExecuteMsg::Seal {
+            bundle_id: TOKEN_ID.into(),
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            msg.clone(),
+        )
+        .unwrap();
+        let err = execute(deps.as_mut(), mock_env(), mock_info(MINTER, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::BundleSealed {});
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            lock_msg(height + 20),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::BundleSealed {});
+        let err = withdraw(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            "a".into(),
+            WithdrawOptions::default(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::BundleSealed {});
+        let msg = Cw20ReceiveMsg {
+            sender: MINTER.into(),
+            amount: Uint128::from(2u128),
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+        };
+        let err = receive_cw20(deps.as_mut(), mock_info(CONTRACT, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::BundleSealed {});
+
+        let res: BundleLockResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), query_msg).unwrap()).unwrap();
+        assert_eq!(res.lock, Some(BundleLock::Sealed {}));
+        assert!(res.active);
+    }
+
+    #[test]
+    fn try_deposit_rights() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+        mint_bundle(deps.as_mut(), TOKEN_ID, ALICE);
+
+        let deposit = |deps: DepsMut, sender: &str| {
+            let msg = Cw20ReceiveMsg {
+                sender: sender.into(),
+                amount: Uint128::from(2u128),
+                msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+            };
+            receive_cw20(deps, mock_info(CONTRACT, &[]), msg)
+        };
+        let depositors = |deps: Deps| {
+            let msg = QueryMsg::BundleDepositors {
+                bundle_id: TOKEN_ID.into(),
+                start_after: None,
+                limit: None,
+            };
+            let res: BundleDepositorsResponse =
+                from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+            res
+        };
+
+        // only the owner can deposit or grant deposit rights
+        let err = deposit(deps.as_mut(), "bob").unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let msg = ExecuteMsg::UpdateDepositors {
+            bundle_id: TOKEN_ID.into(),
+            add: vec!["bob".into()],
+            remove: vec![],
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bob", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), msg).unwrap();
+        assert_eq!(
+            depositors(deps.as_ref()),
+            BundleDepositorsResponse {
+                open: false,
+                depositors: vec!["bob".into()],
+            }
+        );
+
+        deposit(deps.as_mut(), "bob").unwrap();
+        deposit(deps.as_mut(), "bob").unwrap();
+        deposit(deps.as_mut(), ALICE).unwrap();
+        let err = deposit(deps.as_mut(), "carol").unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // anyone can deposit into an open bundle
+        let msg = ExecuteMsg::SetOpenToContributions {
+            bundle_id: TOKEN_ID.into(),
+            open: true,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), msg).unwrap();
+        assert!(depositors(deps.as_ref()).open);
+        deposit(deps.as_mut(), "carol").unwrap();
+
+        // every depositor is recorded
+        let msg = QueryMsg::BundleContributors {
+            bundle_id: TOKEN_ID.into(),
+            start_after: None,
+            limit: None,
+        };
+        let res: BundleContributorsResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(
+            res.contributors,
+            vec![
+                Contributor {
+                    address: ALICE.into(),
+                    deposits: 1,
+                },
+                Contributor {
+                    address: "bob".into(),
+                    deposits: 2,
+                },
+                Contributor {
+                    address: "carol".into(),
+                    deposits: 1,
+                },
+            ]
+        );
+
+        // deposit rights are reset when the bundle changes hands
+        let msg = ExecuteMsg::TransferNft {
+            recipient: MINTER.into(),
+            token_id: TOKEN_ID.into(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), msg).unwrap();
+        assert_eq!(
+            depositors(deps.as_ref()),
+            BundleDepositorsResponse {
+                open: false,
+                depositors: vec![],
+            }
+        );
+        let err = deposit(deps.as_mut(), "bob").unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        deposit(deps.as_mut(), MINTER).unwrap();
+    }
+
+    #[test]
+    fn try_asset_list() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+        mint_bundle(deps.as_mut(), TOKEN_ID, ALICE);
+
+        let deposit_cw20 = |deps: DepsMut| {
+            let msg = Cw20ReceiveMsg {
+                sender: ALICE.into(),
+                amount: Uint128::from(2u128),
+                msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+            };
+            receive_cw20(deps, mock_info(CONTRACT, &[]), msg)
+        };
+        let deposit_cw721 = |deps: DepsMut| {
+            let msg = Cw721ReceiveMsg {
+                sender: ALICE.into(),
+                token_id: "nft".into(),
+                msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+            };
+            receive_cw721(deps, mock_env(), mock_info("nft_contract", &[]), msg)
+        };
+
+        // start from an empty list
+        let msg = ExecuteMsg::UpdateAssetList {
+            filter: None,
+            add: vec![],
+            remove: ASSET_CONTRACTS
+                .iter()
+                .map(|contract| contract.to_string())
+                .collect(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+
+        // only the admin can update the asset list
+        let msg = ExecuteMsg::UpdateAssetList {
+            filter: Some(AssetFilter::Denylist {}),
+            add: vec![CONTRACT.into()],
+            remove: vec![],
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+
+        // denied contracts are refused, others accepted
+        let err = deposit_cw20(deps.as_mut()).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::AssetNotAllowed {
+                contract: CONTRACT.into()
+            }
+        );
+        deposit_cw721(deps.as_mut()).unwrap();
+
+        // with an allowlist only listed contracts are accepted
+        let msg = ExecuteMsg::UpdateAssetList {
+            filter: Some(AssetFilter::Allowlist {}),
+            add: vec![],
+            remove: vec![],
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+        deposit_cw20(deps.as_mut()).unwrap();
+        let err = deposit_cw721(deps.as_mut()).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::AssetNotAllowed {
+                contract: "nft_contract".into()
+            }
+        );
+
+        let msg = QueryMsg::AssetList {
+            start_after: None,
+            limit: None,
+        };
+        let res: AssetListResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(
+            res,
+            AssetListResponse {
+                filter: AssetFilter::Allowlist {},
+                contracts: vec![CONTRACT.into()],
+            }
+        );
+    }
+
+    #[test]
+    fn try_bundle_size_and_withdraw_batch() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+        mint_bundle(deps.as_mut(), TOKEN_ID, ALICE);
+
+        let update = ExecuteMsg::UpdateConfig {
+            mint_policy: None,
+            max_nesting_depth: None,
+            max_bundle_assets: Some(2),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), update).unwrap();
+
+        let deposit_cw20 = |deps: DepsMut, contract: &str| {
+            let msg = Cw20ReceiveMsg {
+                sender: ALICE.into(),
+                amount: Uint128::from(2u128),
+                msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+            };
+            receive_cw20(deps, mock_info(contract, &[]), msg)
+        };
+        deposit_cw20(deps.as_mut(), CONTRACT).unwrap();
+        let msg = Cw721ReceiveMsg {
+            sender: ALICE.into(),
+            token_id: "nft".into(),
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+        };
+        receive_cw721(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("nft_contract", &[]),
+            msg,
+        )
+        .unwrap();
+
+        // more of a bundled asset still fits
+        deposit_cw20(deps.as_mut(), CONTRACT).unwrap();
+        assert_eq!(2, ASSET_COUNTS.load(&deps.storage, "a").unwrap());
+
+        // only the owner can withdraw in batches, at least one asset at a time
+        let err = withdraw_batch(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            "a".into(),
+            0,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidLimit {});
+        let err = withdraw_batch(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            "a".into(),
+            1,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // drain the bundle one asset at a time
+        let res = withdraw_batch(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            "a".into(),
+            1,
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(WasmMsg::Execute {
+                contract_addr: "nft_contract".into(),
+                msg: to_binary(&Cw721ExecuteMsg::TransferNft {
+                    recipient: ALICE.into(),
+                    token_id: "nft".into(),
+                })
+                .unwrap(),
+                funds: vec![],
+            })]
+        );
+        let bundle = load_bundle_contents(&deps.storage, "a").unwrap();
+        assert!(bundle.cw721.is_empty());
+        assert_eq!(bundle.cw20.len(), 1);
+        assert_eq!(1, ASSET_COUNTS.load(&deps.storage, "a").unwrap());
+
+        let res = withdraw_batch(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            "a".into(),
+            1,
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(WasmMsg::Execute {
+                contract_addr: CONTRACT.into(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: ALICE.into(),
+                    amount: Uint128::from(4u128),
+                })
+                .unwrap(),
+                funds: vec![],
+            })]
+        );
+        let bundle = load_bundle_contents(&deps.storage, "a").unwrap();
+        assert!(bundle.cw20.is_empty());
+        assert!(!ASSET_COUNTS.has(&deps.storage, "a"));
+
+        // a full bundle refuses new assets
+        deposit_cw20(deps.as_mut(), CONTRACT).unwrap();
+        deposit_cw20(deps.as_mut(), "other_token").unwrap();
+        let err = deposit_cw20(deps.as_mut(), "third_token").unwrap_err();
+        assert_eq!(err, ContractError::BundleFull { max: 2 });
+    }
+
+    #[test]
+    fn try_withdraw_send_and_split() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+
+        for bundle_id in ["a", "b"] {
+            mint_bundle(deps.as_mut(), bundle_id, ALICE);
+            let payload = to_binary(&DepositCwMsg {
+                bundle_id: bundle_id.into(),
+            })
+            .unwrap();
+            let msg = Cw20ReceiveMsg {
+                sender: ALICE.into(),
+                amount: Uint128::from(10u128),
+                msg: payload.clone(),
+            };
+            receive_cw20(deps.as_mut(), mock_info(CONTRACT, &[]), msg).unwrap();
+            let msg = Cw721ReceiveMsg {
+                sender: ALICE.into(),
+                token_id: bundle_id.into(),
+                msg: payload,
+            };
+            let info = mock_info("nft_contract", &[]);
+            receive_cw721(deps.as_mut(), mock_env(), info, msg).unwrap();
+        }
+        let info = mock_info(ALICE, &coins(9, "uatom"));
+        deposit_native(deps.as_mut(), info, "b".into()).unwrap();
+
+        // send the tokens to a contract with a hook payload
+        let hook = Binary::from(b"repay".to_vec());
+        let msg = ExecuteMsg::Withdraw {
+            bundle_id: "a".into(),
+            recipient: Some("loan_contract".into()),
+            use_send: true,
+            msg: Some(hook.clone()),
+            splits: vec![],
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(WasmMsg::Execute {
+                    contract_addr: "nft_contract".into(),
+                    msg: to_binary(&Cw721ExecuteMsg::SendNft {
+                        contract: "loan_contract".into(),
+                        token_id: "a".into(),
+                        msg: hook.clone(),
+                    })
+                    .unwrap(),
+                    funds: vec![],
+                }),
+                SubMsg::new(WasmMsg::Execute {
+                    contract_addr: CONTRACT.into(),
+                    msg: to_binary(&Cw20ExecuteMsg::Send {
+                        contract: "loan_contract".into(),
+                        amount: Uint128::from(10u128),
+                        msg: hook,
+                    })
+                    .unwrap(),
+                    funds: vec![],
+                }),
+            ]
+        );
+
+        // a hook payload is only delivered with use_send
+        let msg = ExecuteMsg::Withdraw {
+            bundle_id: "b".into(),
+            recipient: None,
+            use_send: false,
+            msg: Some(Binary::from(b"repay".to_vec())),
+            splits: vec![],
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::MsgWithoutSend {});
+
+        // shares must add up to the whole and replace the recipient
+        let split = |recipient: &str, bps: u16| WithdrawSplit {
+            recipient: recipient.into(),
+            bps,
+        };
+        let msg = ExecuteMsg::Withdraw {
+            bundle_id: "b".into(),
+            recipient: None,
+            use_send: false,
+            msg: None,
+            splits: vec![split("bob", 7_000), split("carol", 2_000)],
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidWithdrawSplit {});
+        let msg = ExecuteMsg::Withdraw {
+            bundle_id: "b".into(),
+            recipient: Some("bob".into()),
+            use_send: false,
+            msg: None,
+            splits: vec![split("bob", 7_000), split("carol", 3_000)],
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidWithdrawSplit {});
+
+        // the first recipient gets the nft and the rounding leftovers
+        let msg = ExecuteMsg::Withdraw {
+            bundle_id: "b".into(),
+            recipient: None,
+            use_send: false,
+            msg: None,
+            splits: vec![split("bob", 7_000), split("carol", 3_000)],
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), msg).unwrap();
+        let cw20_transfer = |recipient: &str, amount: u128| {
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: CONTRACT.into(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: recipient.into(),
+                    amount: Uint128::from(amount),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        };
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(WasmMsg::Execute {
+                    contract_addr: "nft_contract".into(),
+                    msg: to_binary(&Cw721ExecuteMsg::TransferNft {
+                        recipient: "bob".into(),
+                        token_id: "b".into(),
+                    })
+                    .unwrap(),
+                    funds: vec![],
+                }),
+                cw20_transfer("bob", 7),
+                cw20_transfer("carol", 3),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "bob".into(),
+                    amount: coins(7, "uatom"),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "carol".into(),
+                    amount: coins(2, "uatom"),
+                }),
+            ]
+        );
     }
 
     #[test]
-    fn try_receive_cw721() {
+    fn try_deposit_native() {
         let mut deps = mock_dependencies(&[]);
         setup_contract(deps.as_mut());
 
@@ -454,130 +5191,739 @@ mod tests {
             mint_msg,
         );
 
-        let info = mock_info(CONTRACT, &[]);
-        let msg = Cw721ReceiveMsg {
-            sender: ALICE.into(),
-            token_id: TOKEN_ID.into(),
-            msg: Binary::from_base64(&"eyJidW5kbGVfaWQiOiAiYSJ9".to_string()).unwrap(),
-        };
+        // no funds attached
+        let info = mock_info(ALICE, &[]);
+        let err = deposit_native(deps.as_mut(), info, "a".into()).unwrap_err();
+        assert_eq!(err, ContractError::NoFunds {});
 
-        // receive cw721 token
-        let res = receive_cw721(deps.as_mut(), info, msg).unwrap();
-        let info = mock_info(CONTRACT, &[]);
-        let msg = Cw721ReceiveMsg {
-            sender: ALICE.into(),
-            token_id: TOKEN_ID.into(),
-            msg: Binary::from_base64(&"eyJidW5kbGVfaWQiOiAiYSJ9".to_string()).unwrap(),
-        };
+        // only the owner can deposit
+        let info = mock_info(MINTER, &coins(10, "uatom"));
+        let err = deposit_native(deps.as_mut(), info, "a".into()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // deposit the same denom twice
+        let info = mock_info(ALICE, &coins(10, "uatom"));
+        let res = deposit_native(deps.as_mut(), info, "a".into()).unwrap();
         assert_eq!(
             res,
             Response::default()
-                .add_attribute("action", "deposit_cw721")
-                .add_attribute("sender", msg.sender)
-                .add_attribute("contract_sender", info.sender.to_string())
-                .add_attribute("token_id", msg.token_id)
+                .add_event(
+                    Event::new("bundle_deposit")
+                        .add_attribute("bundle_id", "a")
+                        .add_attribute("asset_type", "native")
+                        .add_attribute("contract", "uatom")
+                        .add_attribute("token_id", "")
+                        .add_attribute("amount", "10")
+                )
+                .add_attribute("action", "deposit_native")
+                .add_attribute("sender", ALICE)
+                .add_attribute("amount", "10uatom")
                 .add_attribute("bundle_id", "a")
         );
+        let info = mock_info(ALICE, &[coin(5, "uatom"), coin(7, "uluna")]);
+        let _res = deposit_native(deps.as_mut(), info, "a".into()).unwrap();
 
-        // ensure num tokens in bundle is 1
-        let bundle = CW721_BUNDLE
-            .may_load(&deps.storage, "a".into())
-            .unwrap()
-            .unwrap();
-        assert_eq!(1, bundle.len());
+        // ensure coins are merged per denom
+        let bundle = load_bundle_contents(&deps.storage, "a").unwrap();
+        assert_eq!(vec![coin(15, "uatom"), coin(7, "uluna")], bundle.native);
+
+        // withdraw returns the coins with a bank send
+        let info = mock_info(ALICE, &[]);
+        let res = withdraw(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            "a".into(),
+            WithdrawOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: ALICE.into(),
+                amount: vec![coin(15, "uatom"), coin(7, "uluna")],
+            })]
+        );
+        let bundle = load_bundle_contents(&deps.storage, "a").unwrap();
+        assert!(bundle.native.is_empty());
     }
 
     #[test]
-    fn try_receive_cw1155() {
+    fn try_withdraw_assets() {
         let mut deps = mock_dependencies(&[]);
         setup_contract(deps.as_mut());
+        mint_bundle(deps.as_mut(), TOKEN_ID, ALICE);
 
-        let info = mock_info(MINTER, &[]);
-        let mint_msg = cw721_execute_msg::Mint(Cw721MintMsg {
-            token_id: TOKEN_ID.into(),
-            owner: ALICE.into(),
-            extension: None,
-            token_uri: Some("ipfs://QmVKZ5YZYDqdnAQo93kaYbcMtzGgx9kvpAVwoERm5mZezh".to_string()),
-        });
-        let _ = Cw721Contract::<Extension, Empty>::default().execute(
+        // deposit the same cw20 twice and one cw721
+        for _ in 0..2 {
+            let msg = Cw20ReceiveMsg {
+                sender: ALICE.into(),
+                amount: Uint128::from(2u128),
+                msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+            };
+            receive_cw20(deps.as_mut(), mock_info(CONTRACT, &[]), msg).unwrap();
+        }
+        let msg = Cw721ReceiveMsg {
+            sender: ALICE.into(),
+            token_id: "nft".into(),
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+        };
+        receive_cw721(deps.as_mut(), mock_env(), mock_info(CONTRACT, &[]), msg).unwrap();
+
+        // repeated deposits are summed into one entry
+        let bundle = load_bundle_contents(&deps.storage, "a").unwrap();
+        assert_eq!(
+            vec![CW20Wrapper {
+                contract_address: Addr::unchecked(CONTRACT),
+                amount: Uint128::from(4u128),
+            }],
+            bundle.cw20
+        );
+
+        // only the owner can withdraw
+        let assets = BundleAssets {
+            cw20: vec![Cw20Asset {
+                contract_address: CONTRACT.into(),
+                amount: Uint128::from(3u128),
+            }],
+            ..BundleAssets::default()
+        };
+        let err = withdraw_assets(
             deps.as_mut(),
             mock_env(),
-            info,
-            mint_msg,
+            mock_info(MINTER, &[]),
+            "a".into(),
+            assets.clone(),
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // an empty selection or a zero amount moves nothing
+        let err = withdraw_assets(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            "a".into(),
+            BundleAssets::default(),
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NoFunds {});
+        let err = withdraw_assets(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            "a".into(),
+            BundleAssets {
+                cw20: vec![Cw20Asset {
+                    contract_address: CONTRACT.into(),
+                    amount: Uint128::zero(),
+                }],
+                ..BundleAssets::default()
+            },
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NoFunds {});
+
+        // more than held
+        let err = withdraw_assets(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            "a".into(),
+            BundleAssets {
+                cw20: vec![Cw20Asset {
+                    contract_address: CONTRACT.into(),
+                    amount: Uint128::from(5u128),
+                }],
+                ..BundleAssets::default()
+            },
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InsufficientBalance {});
+
+        // unknown nft
+        let err = withdraw_assets(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            "a".into(),
+            BundleAssets {
+                cw721: vec![Cw721Asset {
+                    contract_address: CONTRACT.into(),
+                    token_id: "other".into(),
+                }],
+                ..BundleAssets::default()
+            },
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::AssetNotFound {});
+
+        // partial cw20 withdrawal
+        let res = withdraw_assets(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            "a".into(),
+            assets,
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(WasmMsg::Execute {
+                contract_addr: CONTRACT.into(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: ALICE.into(),
+                    amount: Uint128::from(3u128),
+                })
+                .unwrap(),
+                funds: vec![],
+            })]
+        );
+        let bundle = load_bundle_contents(&deps.storage, "a").unwrap();
+        assert_eq!(
+            vec![CW20Wrapper {
+                contract_address: Addr::unchecked(CONTRACT),
+                amount: Uint128::from(1u128),
+            }],
+            bundle.cw20
         );
 
-        let info = mock_info(CONTRACT, &[]);
+        // the nft is left untouched
+        assert_eq!(1, bundle.cw721.len());
+
+        // a token that refuses transfers can be dropped so the rest can still be withdrawn
+        let res = withdraw_assets(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            "a".into(),
+            BundleAssets {
+                cw20: vec![Cw20Asset {
+                    contract_address: CONTRACT.into(),
+                    amount: Uint128::from(1u128),
+                }],
+                ..BundleAssets::default()
+            },
+            true,
+        )
+        .unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(res.attributes[0], attr("action", "discard_assets"));
+        let bundle = load_bundle_contents(&deps.storage, "a").unwrap();
+        assert!(bundle.cw20.is_empty());
+        assert_eq!(Some(1), ASSET_COUNTS.may_load(&deps.storage, "a").unwrap());
+    }
+
+    #[test]
+    fn try_withdraw_cw1155_from_multiple_contracts() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+        mint_bundle(deps.as_mut(), TOKEN_ID, ALICE);
+
+        // deposit tokens from two different cw1155 contracts
         let msg = Cw1155BatchReceiveMsg {
             operator: ALICE.into(),
-            from: None,
-            batch: vec![(TOKEN_ID.into(), Uint128::from(2u128))],
-            msg: Binary::from_base64(&"eyJidW5kbGVfaWQiOiAiYSJ9".to_string()).unwrap(),
+            from: Some(ALICE.into()),
+            batch: vec![
+                ("gold".into(), Uint128::from(2u128)),
+                ("silver".into(), Uint128::from(3u128)),
+            ],
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
         };
-
-        // receive cw721 token
-        let res = receive_cw1155(deps.as_mut(), info, msg).unwrap();
-        let info = mock_info(CONTRACT, &[]);
+        receive_cw1155(deps.as_mut(), mock_info("cw1155_one", &[]), msg).unwrap();
         let msg = Cw1155BatchReceiveMsg {
             operator: ALICE.into(),
-            from: None,
-            batch: vec![(TOKEN_ID.into(), Uint128::from(2u128))],
-            msg: Binary::from_base64(&"eyJidW5kbGVfaWQiOiAiYSJ9".to_string()).unwrap(),
+            from: Some(ALICE.into()),
+            batch: vec![("gold".into(), Uint128::from(4u128))],
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+        };
+        receive_cw1155(deps.as_mut(), mock_info("cw1155_two", &[]), msg).unwrap();
+
+        let env = mock_env();
+        let res = withdraw(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ALICE, &[]),
+            "a".into(),
+            WithdrawOptions::default(),
+        )
+        .unwrap();
+
+        // one batch per originating contract, sent from this contract to the owner
+        let batch_send = |contract_addr: &str, batch: Vec<(String, Uint128)>| {
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: contract_addr.into(),
+                msg: to_binary(&Cw1155ExecuteMsg::BatchSendFrom {
+                    from: env.contract.address.to_string(),
+                    to: ALICE.into(),
+                    batch,
+                    msg: None,
+                })
+                .unwrap(),
+                funds: vec![],
+            })
         };
         assert_eq!(
-            res,
-            Response::default()
-                .add_attribute("action", "deposit_cw1155")
-                .add_attribute("sender", msg.operator)
-                .add_attribute("contract_sender", info.sender.to_string())
-                .add_attribute("bundle_id", "a")
+            res.messages,
+            vec![
+                batch_send(
+                    "cw1155_one",
+                    vec![
+                        ("gold".into(), Uint128::from(2u128)),
+                        ("silver".into(), Uint128::from(3u128)),
+                    ]
+                ),
+                batch_send("cw1155_two", vec![("gold".into(), Uint128::from(4u128))]),
+            ]
         );
+    }
 
-        // ensure num tokens in bundle is 1
-        let bundle = CW1155_BUNDLE
-            .may_load(&deps.storage, "a".into())
-            .unwrap()
+    #[test]
+    fn transfer_and_approve_bundle() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+        mint_bundle(deps.as_mut(), TOKEN_ID, ALICE);
+
+        // only the owner can transfer
+        let msg = ExecuteMsg::TransferNft {
+            recipient: MINTER.into(),
+            token_id: TOKEN_ID.into(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(MINTER, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // approve a marketplace, which can then send the bundle on
+        let msg = ExecuteMsg::Approve {
+            spender: "marketplace".into(),
+            token_id: TOKEN_ID.into(),
+            expires: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), msg).unwrap();
+
+        let msg = ExecuteMsg::SendNft {
+            contract: "buyer_contract".into(),
+            token_id: TOKEN_ID.into(),
+            msg: to_binary("buy").unwrap(),
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("marketplace", &[]),
+            msg,
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(
+                Cw721ReceiveMsg {
+                    sender: "marketplace".into(),
+                    token_id: TOKEN_ID.into(),
+                    msg: to_binary("buy").unwrap(),
+                }
+                .into_cosmos_msg("buyer_contract")
+                .unwrap()
+            )]
+        );
+
+        let token = Cw721Contract::<Extension, Empty>::default()
+            .tokens
+            .load(&deps.storage, TOKEN_ID)
             .unwrap();
-        assert_eq!(1, bundle.len());
+        assert_eq!(token.owner, Addr::unchecked("buyer_contract"));
+        assert!(token.approvals.is_empty());
+
+        // operators approved for all can transfer as well
+        let msg = ExecuteMsg::ApproveAll {
+            operator: ALICE.into(),
+            expires: None,
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("buyer_contract", &[]),
+            msg,
+        )
+        .unwrap();
+        let msg = ExecuteMsg::TransferNft {
+            recipient: ALICE.into(),
+            token_id: TOKEN_ID.into(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), msg).unwrap();
+
+        // the new owner can withdraw the bundle contents
+        withdraw(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            "a".into(),
+            WithdrawOptions::default(),
+        )
+        .unwrap();
     }
 
     #[test]
-    fn try_withdraw() {
+    fn withdraw_refused_while_approved() {
         let mut deps = mock_dependencies(&[]);
         setup_contract(deps.as_mut());
+        mint_bundle(deps.as_mut(), TOKEN_ID, ALICE);
 
-        let info = mock_info(MINTER, &[]);
-        let mint_msg = cw721_execute_msg::Mint(Cw721MintMsg {
+        // a listed bundle cannot be emptied or burnt before the sale
+        let msg = ExecuteMsg::Approve {
+            spender: "marketplace".into(),
             token_id: TOKEN_ID.into(),
-            owner: ALICE.into(),
+            expires: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), msg).unwrap();
+        let err = withdraw(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            TOKEN_ID.into(),
+            WithdrawOptions::default(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::BundleApproved {});
+        let err = unbundle(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            TOKEN_ID.into(),
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::BundleApproved {});
+
+        // an operator approved for all the owner's tokens does not block withdrawals
+        let msg = ExecuteMsg::Revoke {
+            spender: "marketplace".into(),
+            token_id: TOKEN_ID.into(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), msg).unwrap();
+        let msg = ExecuteMsg::ApproveAll {
+            operator: "marketplace".into(),
+            expires: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), msg).unwrap();
+        withdraw(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            TOKEN_ID.into(),
+            WithdrawOptions::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn try_unbundle() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+        let update = ExecuteMsg::UpdateConfig {
+            mint_policy: Some(MintPolicy::Public {
+                max_per_owner: Some(1),
+            }),
+            max_nesting_depth: None,
+            max_bundle_assets: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), update).unwrap();
+        let create = ExecuteMsg::CreateBundle {
+            token_uri: None,
             extension: None,
-            token_uri: Some("ipfs://QmVKZ5YZYDqdnAQo93kaYbcMtzGgx9kvpAVwoERm5mZezh".to_string()),
-        });
-        let _ = Cw721Contract::<Extension, Empty>::default().execute(
+        };
+        let res = execute(
             deps.as_mut(),
             mock_env(),
-            info,
-            mint_msg,
+            mock_info(ALICE, &[]),
+            create.clone(),
+        )
+        .unwrap();
+        assert_eq!(res.attributes[2], attr("bundle_id", "1"));
+
+        let msg = Cw721ReceiveMsg {
+            sender: ALICE.into(),
+            token_id: "nft".into(),
+            msg: to_binary(&DepositCwMsg {
+                bundle_id: "1".into(),
+            })
+            .unwrap(),
+        };
+        receive_cw721(deps.as_mut(), mock_env(), mock_info(CONTRACT, &[]), msg).unwrap();
+        deposit_native(
+            deps.as_mut(),
+            mock_info(ALICE, &coins(10, "uatom")),
+            "1".into(),
+        )
+        .unwrap();
+        let owner = mock_info(ALICE, &[]);
+        let msg = ExecuteMsg::SetOpenToContributions {
+            bundle_id: "1".into(),
+            open: true,
+        };
+        execute(deps.as_mut(), mock_env(), owner.clone(), msg).unwrap();
+        let msg = ExecuteMsg::UpdateDepositors {
+            bundle_id: "1".into(),
+            add: vec!["bob_address".into()],
+            remove: vec![],
+        };
+        execute(deps.as_mut(), mock_env(), owner.clone(), msg).unwrap();
+        let msg = ExecuteMsg::Lock {
+            bundle_id: "1".into(),
+            until: Expiration::AtHeight(mock_env().block.height + 1),
+        };
+        execute(deps.as_mut(), mock_env(), owner, msg).unwrap();
+        let mut env = mock_env();
+        env.block.height += 2;
+
+        // only the owner can unbundle
+        let err = unbundle(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(MINTER, &[]),
+            "1".into(),
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        let res = unbundle(
+            deps.as_mut(),
+            env,
+            mock_info(ALICE, &[]),
+            "1".into(),
+            Some("bob_address".into()),
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(WasmMsg::Execute {
+                    contract_addr: CONTRACT.into(),
+                    msg: to_binary(&Cw721ExecuteMsg::TransferNft {
+                        recipient: "bob_address".into(),
+                        token_id: "nft".into(),
+                    })
+                    .unwrap(),
+                    funds: vec![],
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "bob_address".into(),
+                    amount: coins(10, "uatom"),
+                }),
+            ]
         );
 
-        let info = mock_info(MINTER, &[]);
+        // the bundle token is burned and its entries removed
+        let cw721_contract = Cw721Contract::<Extension, Empty>::default();
+        assert_eq!(
+            None,
+            cw721_contract.tokens.may_load(&deps.storage, "1").unwrap()
+        );
+        assert_eq!(0, cw721_contract.token_count(&deps.storage).unwrap());
+        let bundle = load_bundle_contents(&deps.storage, "1").unwrap();
+        assert!(bundle.cw721.is_empty());
+        assert!(bundle.native.is_empty());
+        assert!(!ASSET_COUNTS.has(&deps.storage, "1"));
+        assert!(!LOCKS.has(&deps.storage, "1"));
+        assert!(!OPEN_BUNDLES.has(&deps.storage, "1"));
+        let bob = Addr::unchecked("bob_address");
+        assert!(!ALLOWED_DEPOSITORS.has(&deps.storage, ("1", &bob)));
+        let alice = Addr::unchecked(ALICE);
+        assert!(!CONTRIBUTORS.has(&deps.storage, ("1", &alice)));
+        assert!(!BUNDLE_CREATORS.has(&deps.storage, "1"));
+        assert!(!CREATED_BUNDLES.has(&deps.storage, &alice));
+
+        // the creator can mint again under the cap
+        let res = execute(deps.as_mut(), mock_env(), mock_info(ALICE, &[]), create).unwrap();
+        assert_eq!(res.attributes[2], attr("bundle_id", "2"));
+    }
+
+    #[test]
+    fn query_bundle_contents_and_info() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+        mint_bundle(deps.as_mut(), TOKEN_ID, ALICE);
+        mint_bundle(deps.as_mut(), "b", ALICE);
+
+        let msg = Cw20ReceiveMsg {
+            sender: ALICE.into(),
+            amount: Uint128::from(2u128),
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+        };
+        receive_cw20(deps.as_mut(), mock_info(CONTRACT, &[]), msg).unwrap();
         let msg = Cw721ReceiveMsg {
             sender: ALICE.into(),
-            token_id: TOKEN_ID.into(),
-            msg: Binary::from_base64(&"eyJidW5kbGVfaWQiOiAiYSJ9".to_string()).unwrap(),
+            token_id: "nft".into(),
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
         };
+        receive_cw721(deps.as_mut(), mock_env(), mock_info(CONTRACT, &[]), msg).unwrap();
 
-        // receive cw721 token
-        let _res = receive_cw721(deps.as_mut(), info, msg).unwrap();
+        let msg = QueryMsg::BundleContents {
+            bundle_id: "a".into(),
+            recursive: None,
+        };
+        let res: BundleContentsResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        let contents = BundleContentsResponse {
+            native: vec![],
+            cw20: vec![CW20Wrapper {
+                contract_address: Addr::unchecked(CONTRACT),
+                amount: Uint128::from(2u128),
+            }],
+            cw721: vec![CW721Wrapper {
+                contract_address: Addr::unchecked(CONTRACT),
+                token_id: "nft".into(),
+            }],
+            cw1155: vec![],
+            nested: vec![],
+        };
+        assert_eq!(contents, res);
 
-        // withdraw
-        let info = mock_info(ALICE, &[]);
-        let _res = withdraw(deps.as_mut(), mock_env(), info, "a".into()).unwrap();
+        // unknown bundles are an error
+        let msg = QueryMsg::BundleContents {
+            bundle_id: "unknown".into(),
+            recursive: None,
+        };
+        query(deps.as_ref(), mock_env(), msg).unwrap_err();
 
-        // ensure num tokens in bundle is 0
-        let bundle = CW721_BUNDLE
-            .may_load(&deps.storage, "a".into())
-            .unwrap()
-            .unwrap();
-        assert_eq!(0, bundle.len());
+        let msg = QueryMsg::BundlesInfo {
+            bundle_ids: vec!["a".into(), "b".into()],
+            include_expired: None,
+        };
+        let res: BundlesInfoResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(2, res.bundles.len());
+        assert_eq!("a", res.bundles[0].bundle_id);
+        assert_eq!(ALICE, res.bundles[0].info.access.owner);
+        assert_eq!(contents, res.bundles[0].contents);
+        assert_eq!("b", res.bundles[1].bundle_id);
+        assert!(res.bundles[1].contents.cw721.is_empty());
+
+        // too many bundles at once
+        let msg = QueryMsg::BundlesInfo {
+            bundle_ids: vec!["a".into(); MAX_LIMIT as usize + 1],
+            include_expired: None,
+        };
+        query(deps.as_ref(), mock_env(), msg).unwrap_err();
+
+        // cw721 queries are still forwarded
+        let res: NumTokensResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::NumTokens {}).unwrap())
+                .unwrap();
+        assert_eq!(2, res.count);
+    }
+
+    #[test]
+    fn query_bundles_containing() {
+        let mut deps = mock_dependencies(&[]);
+        setup_contract(deps.as_mut());
+
+        for bundle_id in ["a", "b", "c"] {
+            mint_bundle(deps.as_mut(), bundle_id, ALICE);
+            let msg = Cw20ReceiveMsg {
+                sender: ALICE.into(),
+                amount: Uint128::from(2u128),
+                msg: to_binary(&DepositCwMsg {
+                    bundle_id: bundle_id.into(),
+                })
+                .unwrap(),
+            };
+            receive_cw20(deps.as_mut(), mock_info(CONTRACT, &[]), msg).unwrap();
+        }
+        let msg = Cw721ReceiveMsg {
+            sender: ALICE.into(),
+            token_id: "nft".into(),
+            msg: Binary::from_base64("eyJidW5kbGVfaWQiOiAiYSJ9").unwrap(),
+        };
+        receive_cw721(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("nft_contract", &[]),
+            msg,
+        )
+        .unwrap();
+
+        let bundles_containing = |deps: Deps, token_id: Option<&str>, start_after: Option<&str>| {
+            let msg = QueryMsg::BundlesContaining {
+                contract_address: if token_id.is_some() {
+                    "nft_contract".into()
+                } else {
+                    CONTRACT.into()
+                },
+                token_id: token_id.map(String::from),
+                start_after: start_after.map(String::from),
+                limit: Some(2),
+            };
+            let res: BundlesResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+            res.bundle_ids
+        };
+
+        // paginate through the cw20 holders
+        assert_eq!(
+            vec!["a", "b"],
+            bundles_containing(deps.as_ref(), None, None)
+        );
+        assert_eq!(
+            vec!["c"],
+            bundles_containing(deps.as_ref(), None, Some("b"))
+        );
+
+        // find the nft
+        assert_eq!(
+            vec!["a"],
+            bundles_containing(deps.as_ref(), Some("nft"), None)
+        );
+        assert!(bundles_containing(deps.as_ref(), Some("other"), None).is_empty());
+
+        // the index follows withdrawals
+        withdraw(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            "a".into(),
+            WithdrawOptions::default(),
+        )
+        .unwrap();
+        assert!(bundles_containing(deps.as_ref(), Some("nft"), None).is_empty());
+        assert_eq!(
+            vec!["b", "c"],
+            bundles_containing(deps.as_ref(), None, None)
+        );
+
+        let assets = BundleAssets {
+            cw20: vec![Cw20Asset {
+                contract_address: CONTRACT.into(),
+                amount: Uint128::from(1u128),
+            }],
+            ..BundleAssets::default()
+        };
+        withdraw_assets(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            "b".into(),
+            assets.clone(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            vec!["b", "c"],
+            bundles_containing(deps.as_ref(), None, None)
+        );
+        withdraw_assets(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ALICE, &[]),
+            "b".into(),
+            assets,
+            false,
+        )
+        .unwrap();
+        assert_eq!(vec!["c"], bundles_containing(deps.as_ref(), None, None));
     }
 }
@@ -15,11 +15,98 @@ pub enum ContractError {
     #[error("Expired")]
     Expired {},
 
+    #[error("Contract is paused")]
+    Paused {},
+
+    #[error("Public minting is disabled")]
+    PublicMintDisabled {},
+
+    #[error("Bundle cannot hold more than {max} distinct assets")]
+    BundleFull { max: u32 },
+
+    #[error("Limit must be at least 1")]
+    InvalidLimit {},
+
+    #[error("No transfer is pending")]
+    NoPendingTransfer {},
+
+    #[error("Assets of {contract} cannot be deposited")]
+    AssetNotAllowed { contract: String },
+
+    #[error("Cannot mint more than {max} bundles")]
+    MintLimitReached { max: u32 },
+
+    #[error("Mint fee of {fee} required")]
+    MintFeeRequired { fee: String },
+
+    #[error("Bundles cannot be created by a deposit while a mint price is set")]
+    MintPriceOnDeposit {},
+
+    #[error("Bundles can only be created by a capped deposit for allowlisted assets")]
+    CappedDepositNotAllowlisted {},
+
+    #[error("Invalid fee settings: {reason}")]
+    InvalidFees { reason: String },
+
+    #[error("Invalid config: {reason}")]
+    InvalidConfig { reason: String },
+
+    #[error("No funds expected")]
+    UnexpectedFunds {},
+
+    #[error("No funds sent")]
+    NoFunds {},
+
+    #[error("Asset not found in bundle")]
+    AssetNotFound {},
+
+    #[error("Asset is already held by a bundle")]
+    AssetAlreadyBundled {},
+
+    #[error("Bundle holds less than the requested amount")]
+    InsufficientBalance {},
+
+    #[error("Split amounts must add up to the amount of a single fungible token received")]
+    InvalidSplit {},
+
+    #[error("Recipient shares must add up to 10000 basis points and replace `recipient`")]
+    InvalidWithdrawSplit {},
+
+    #[error("A withdraw `msg` is only passed on with `use_send`")]
+    MsgWithoutSend {},
+
+    #[error("A bundle cannot be nested inside itself")]
+    NestingCycle {},
+
+    #[error("Bundles cannot be nested more than {max} levels deep")]
+    NestingTooDeep { max: u32 },
+
+    #[error("Bundle is locked")]
+    BundleLocked {},
+
+    #[error("Bundle is sealed")]
+    BundleSealed {},
+
+    #[error("A lock can only be extended")]
+    LockNotExtended {},
+
+    #[error("Bundle cannot be withdrawn from while it is approved for transfer")]
+    BundleApproved {},
+
     #[error("DecodeError")]
     DecodeError {},
 
     #[error("SerdeJsonError")]
     SerdeJsonError {},
+
+    #[error("Cannot migrate from contract {contract}")]
+    InvalidMigrationContract { contract: String },
+
+    #[error("Cannot migrate from version {from} to older version {to}")]
+    CannotDowngrade { from: String, to: String },
+
+    #[error("Invalid version: {0}")]
+    InvalidVersion(String),
 }
 
 impl From<cw721_base::ContractError> for ContractError {
@@ -44,9 +131,13 @@ impl From<base64::DecodeError> for ContractError {
 }
 
 impl From<serde_json_wasm::de::Error> for ContractError {
-    fn from(err: serde_json_wasm::de::Error) -> Self {
-        match err {
-            _ => ContractError::SerdeJsonError {},
-        }
+    fn from(_err: serde_json_wasm::de::Error) -> Self {
+        ContractError::SerdeJsonError {}
+    }
+}
+
+impl From<semver::Error> for ContractError {
+    fn from(err: semver::Error) -> Self {
+        ContractError::InvalidVersion(err.to_string())
     }
 }
@@ -0,0 +1,18 @@
+use cosmwasm_std::{StdResult, Storage};
+use semver::Version;
+
+pub mod v0_1;
+pub mod v0_2;
+
+/// Run every storage transformation needed to bring state written by `from` up to date,
+/// oldest first
+pub fn upgrade_storage(storage: &mut dyn Storage, from: &Version) -> StdResult<()> {
+    if *from < Version::new(0, 2, 0) {
+        v0_1::migrate_bundles(storage)?;
+    }
+    if *from < Version::new(0, 3, 0) {
+        v0_2::migrate_config(storage)?;
+        v0_2::count_bundle_assets(storage)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use cosmwasm_std::{Coin, Order, StdResult, Storage};
+use cw_storage_plus::Map;
+
+use crate::contract::{add_cw1155, add_cw20, add_native};
+use crate::state::{cw721_bundle, CW1155Wrapper, CW20Wrapper, CW721Wrapper};
+
+// up to 0.1.0 every asset of a bundle was kept in one vector under the bundle id
+const CW20_BUNDLE: Map<String, Vec<CW20Wrapper>> = Map::new("cw20_bundle");
+const CW721_BUNDLE: Map<String, Vec<CW721Wrapper>> = Map::new("cw721_bundle");
+const CW1155_BUNDLE: Map<String, Vec<CW1155Wrapper>> = Map::new("cw1155_bundle");
+const NATIVE_BUNDLE: Map<String, Vec<Coin>> = Map::new("native_bundle");
+
+/// Move the bundle vectors into per-asset entries, summing repeated fungible deposits
+pub fn migrate_bundles(storage: &mut dyn Storage) -> StdResult<()> {
+    for (bundle_id, assets) in drain(storage, CW20_BUNDLE)? {
+        for asset in assets {
+            add_cw20(storage, &bundle_id, &asset.contract_address, asset.amount)?;
+        }
+    }
+    for (bundle_id, assets) in drain(storage, CW721_BUNDLE)? {
+        for asset in assets {
+            cw721_bundle().save(
+                storage,
+                (&bundle_id, &asset.contract_address, &asset.token_id),
+                &asset,
+            )?;
+        }
+    }
+    for (bundle_id, assets) in drain(storage, CW1155_BUNDLE)? {
+        for asset in assets {
+            add_cw1155(
+                storage,
+                &bundle_id,
+                &asset.contract_address,
+                &asset.token_id,
+                asset.amount,
+            )?;
+        }
+    }
+    for (bundle_id, coins) in drain(storage, NATIVE_BUNDLE)? {
+        for coin in coins {
+            add_native(storage, &bundle_id, &coin)?;
+        }
+    }
+    Ok(())
+}
+
+/// Load and remove every entry of a legacy map
+fn drain<T>(storage: &mut dyn Storage, map: Map<String, Vec<T>>) -> StdResult<Vec<(String, Vec<T>)>>
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+{
+    let entries = map
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (key, value) = item?;
+            Ok((String::from_utf8(key)?, value))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    for (bundle_id, _) in entries.iter() {
+        map.remove(storage, bundle_id.clone());
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::{coin, Addr, Uint128};
+
+    use crate::contract::load_bundle_contents;
+
+    #[test]
+    fn migrate_bundle_vectors() {
+        let mut storage = MockStorage::new();
+        let cw20 = |amount: u128| CW20Wrapper {
+            contract_address: Addr::unchecked("cw20_contract"),
+            amount: Uint128::from(amount),
+        };
+        let cw721 = CW721Wrapper {
+            contract_address: Addr::unchecked("cw721_contract"),
+            token_id: "nft".into(),
+        };
+        let cw1155 = CW1155Wrapper {
+            contract_address: Addr::unchecked("cw1155_contract"),
+            token_id: "gold".into(),
+            amount: Uint128::from(5u128),
+        };
+        CW20_BUNDLE
+            .save(&mut storage, "a".into(), &vec![cw20(2), cw20(3)])
+            .unwrap();
+        CW20_BUNDLE
+            .save(&mut storage, "b".into(), &vec![cw20(7)])
+            .unwrap();
+        CW721_BUNDLE
+            .save(&mut storage, "a".into(), &vec![cw721.clone()])
+            .unwrap();
+        CW1155_BUNDLE
+            .save(&mut storage, "a".into(), &vec![cw1155.clone()])
+            .unwrap();
+        NATIVE_BUNDLE
+            .save(&mut storage, "a".into(), &vec![coin(10, "uatom")])
+            .unwrap();
+        // empty vectors were left behind by withdrawals
+        CW721_BUNDLE
+            .save(&mut storage, "b".into(), &vec![])
+            .unwrap();
+
+        migrate_bundles(&mut storage).unwrap();
+
+        let bundle = load_bundle_contents(&storage, "a").unwrap();
+        assert_eq!(vec![cw20(5)], bundle.cw20);
+        assert_eq!(vec![cw721], bundle.cw721);
+        assert_eq!(vec![cw1155], bundle.cw1155);
+        assert_eq!(vec![coin(10, "uatom")], bundle.native);
+        let bundle = load_bundle_contents(&storage, "b").unwrap();
+        assert_eq!(vec![cw20(7)], bundle.cw20);
+        assert!(bundle.cw721.is_empty());
+
+        // the old vectors are gone
+        assert_eq!(
+            0,
+            CW20_BUNDLE
+                .range(&storage, None, None, Order::Ascending)
+                .count()
+        );
+        assert_eq!(
+            0,
+            CW721_BUNDLE
+                .range(&storage, None, None, Order::Ascending)
+                .count()
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{Empty, Order, StdResult, Storage};
+use cw721_base::{Cw721Contract, Extension};
+
+use cw_storage_plus::Prefix;
+
+use crate::state::{
+    bundle_id_from_key, AssetFilter, Config, FeeConfig, MintPolicy, ASSET_COUNTS, CONFIG,
+    DEFAULT_MAX_BUNDLE_ASSETS, DEFAULT_MAX_NESTING_DEPTH,
+};
+
+// namespaces of the per-asset maps, keyed by bundle id first
+const ASSET_NAMESPACES: [&str; 4] = [
+    "cw20_assets",
+    "cw721_assets",
+    "cw1155_assets",
+    "native_assets",
+];
+
+/// Store a config for instances that had none, with the cw721 minter as admin
+pub fn migrate_config(storage: &mut dyn Storage) -> StdResult<()> {
+    let minter = Cw721Contract::<Extension, Empty>::default()
+        .minter
+        .load(storage)?;
+    let config = Config {
+        admin: minter,
+        mint_policy: MintPolicy::Minter {},
+        fees: FeeConfig::default(),
+        paused: false,
+        max_nesting_depth: DEFAULT_MAX_NESTING_DEPTH,
+        max_bundle_assets: DEFAULT_MAX_BUNDLE_ASSETS,
+        asset_filter: AssetFilter::default(),
+    };
+    CONFIG.save(storage, &config)
+}
+
+/// Store the number of distinct assets of every bundle, which earlier versions did not track
+pub fn count_bundle_assets(storage: &mut dyn Storage) -> StdResult<()> {
+    // maps keyed by three elements cannot be ranged whole, so all are read by namespace
+    let mut counts: BTreeMap<String, u32> = BTreeMap::new();
+    for namespace in ASSET_NAMESPACES {
+        let keys: Vec<Vec<u8>> = Prefix::<Empty>::new(namespace.as_bytes(), &[])
+            .keys(storage, None, None, Order::Ascending)
+            .collect();
+        for key in keys {
+            *counts.entry(bundle_id_from_key(&key)?).or_default() += 1;
+        }
+    }
+    for (bundle_id, count) in counts {
+        ASSET_COUNTS.save(storage, &bundle_id, &count)?;
+    }
+    Ok(())
+}
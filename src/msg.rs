@@ -1,37 +1,361 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use std::convert::TryFrom;
+
+use cosmwasm_std::{Addr, Binary, Coin, StdError, Uint128};
+
 use cw20::Cw20ReceiveMsg;
 
-use cw721::Cw721ReceiveMsg;
+use cw721::{AllNftInfoResponse, Cw721ReceiveMsg, Expiration};
 use cw721_base::msg::MintMsg as Cw721MintMsg;
 use cw721_base::Extension;
 
-use cw1155::Cw1155BatchReceiveMsg;
+use cw1155::{Cw1155BatchReceiveMsg, Cw1155ReceiveMsg};
+
+use crate::state::{
+    AssetFilter, BundleLock, CW1155Wrapper, CW20Wrapper, CW721Wrapper, MintPolicy, Role,
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct MintMsg {
     pub base: Cw721MintMsg<Extension>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw20Asset {
+    pub contract_address: String,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw721Asset {
+    pub contract_address: String,
+    pub token_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw1155Asset {
+    pub contract_address: String,
+    pub token_id: String,
+    pub amount: Uint128,
+}
+
+/// A price in native coins or in a cw20 token
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Price {
+    Native(Coin),
+    Cw20(Cw20Asset),
+}
+
+/// A selection of assets held by a bundle
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct BundleAssets {
+    pub native: Vec<Coin>,
+    pub cw20: Vec<Cw20Asset>,
+    pub cw721: Vec<Cw721Asset>,
+    pub cw1155: Vec<Cw1155Asset>,
+}
+
+/// Where `Withdraw` sends the assets of a bundle
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct WithdrawOptions {
+    pub recipient: Option<String>,
+    pub use_send: bool,
+    pub msg: Option<Binary>,
+    pub splits: Vec<WithdrawSplit>,
+}
+
+/// A recipient of a split withdraw and its share of every fungible asset
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WithdrawSplit {
+    pub recipient: String,
+    /// Share in basis points, the shares of all recipients add up to 10000
+    pub bps: u16,
+}
+
+/// A `receive` hook call, told apart by its fields as cw20 and cw1155 share the name
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(untagged, try_from = "ReceiveFields")]
+pub enum ReceiveMsg {
+    Cw20(Cw20ReceiveMsg),
+    Cw1155(Cw1155ReceiveMsg),
+}
+
+/// Fields of either `receive` payload. Untagged enums cannot be read on chain, so the
+/// payload is read as one struct and split by the sender field it carries.
+#[derive(Deserialize)]
+struct ReceiveFields {
+    sender: Option<String>,
+    operator: Option<String>,
+    from: Option<String>,
+    token_id: Option<String>,
+    amount: Uint128,
+    msg: Binary,
+}
+
+impl TryFrom<ReceiveFields> for ReceiveMsg {
+    type Error = StdError;
+
+    fn try_from(fields: ReceiveFields) -> Result<Self, Self::Error> {
+        match (fields.sender, fields.operator, fields.token_id) {
+            (Some(sender), None, None) => Ok(ReceiveMsg::Cw20(Cw20ReceiveMsg {
+                sender,
+                amount: fields.amount,
+                msg: fields.msg,
+            })),
+            (None, Some(operator), Some(token_id)) => Ok(ReceiveMsg::Cw1155(Cw1155ReceiveMsg {
+                operator,
+                from: fields.from,
+                token_id,
+                amount: fields.amount,
+                msg: fields.msg,
+            })),
+            _ => Err(StdError::parse_err(
+                "ReceiveMsg",
+                "expected a cw20 or cw1155 receive message",
+            )),
+        }
+    }
+}
+
+/// Payload of the `Receive`, `ReceiveNft` and `BatchReceive` hooks, telling the contract
+/// where the received tokens go
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DepositMsg {
+    /// Deposit into an existing bundle owned by the sender
+    Deposit { bundle_id: String },
+    /// Mint a new bundle to `owner`, or the sender if unset, and deposit into it. Counts
+    /// against the owner's cap, and while a cap is set only allowlisted assets may do so.
+    CreateBundle {
+        bundle_id: String,
+        owner: Option<String>,
+        token_uri: Option<String>,
+        #[serde(default)]
+        extension: Extension,
+    },
+    /// Spread a cw20 amount, or the amount of a single cw1155 token, over bundles owned
+    /// by the sender. The amounts must add up to the amount received.
+    Split { splits: Vec<BundleSplit> },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BundleSplit {
+    pub bundle_id: String,
+    pub amount: Uint128,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
+    /// Name of the bundle NFT contract
     pub name: String,
+    /// Symbol of the bundle NFT contract
     pub symbol: String,
+    /// The only address allowed to mint bundles
+    pub minter: String,
+    /// May update the config, defaults to the sender
+    pub admin: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
     Mint(MintMsg),
+    /// Mint a bundle to the sender when the mint policy is public. The bundle id is
+    /// taken from a counter.
+    CreateBundle {
+        token_uri: Option<String>,
+        #[serde(default)]
+        extension: Extension,
+    },
+
+    /// Transfer is a base message to move a token to another account without triggering actions
+    TransferNft {
+        recipient: String,
+        token_id: String,
+    },
+    /// Send is a base message to transfer a token to a contract and trigger an action
+    /// on the receiving contract.
+    SendNft {
+        contract: String,
+        token_id: String,
+        msg: Binary,
+    },
+    /// Allows operator to transfer / send the token from the owner's account.
+    /// If expiration is set, then this allowance has a time/height limit
+    Approve {
+        spender: String,
+        token_id: String,
+        expires: Option<Expiration>,
+    },
+    /// Remove previously granted Approval
+    Revoke {
+        spender: String,
+        token_id: String,
+    },
+    /// Allows operator to transfer / send any token from the owner's account.
+    /// If expiration is set, then this allowance has a time/height limit
+    ApproveAll {
+        operator: String,
+        expires: Option<Expiration>,
+    },
+    /// Remove previously granted ApproveAll permission
+    RevokeAll {
+        operator: String,
+    },
 
-    Receive(Cw20ReceiveMsg),
+    /// cw20 and single token cw1155 hook, both call it `receive`
+    Receive(ReceiveMsg),
     ReceiveNft(Cw721ReceiveMsg),
     BatchReceive(Cw1155BatchReceiveMsg),
 
-    Withdraw { bundle_id: String },
+    /// Deposit the native coins attached to this message into a bundle
+    DepositNative {
+        bundle_id: String,
+    },
+
+    /// Release every asset in the bundle to `recipient`, or the sender if unset. Refused
+    /// while a spender is approved for the bundle
+    Withdraw {
+        bundle_id: String,
+        #[serde(default)]
+        recipient: Option<String>,
+        /// Send cw20 and cw721 tokens with `Send` and `SendNft`, and cw1155 tokens with
+        /// their receive hook, passing `msg` to the receiving contract. `msg` is refused
+        /// without `use_send`.
+        #[serde(default)]
+        use_send: bool,
+        #[serde(default)]
+        msg: Option<Binary>,
+        /// Split every fungible asset across several recipients instead of `recipient`.
+        /// cw721 tokens and rounding leftovers go to the first recipient.
+        #[serde(default)]
+        splits: Vec<WithdrawSplit>,
+    },
+    /// Like `Withdraw`, but only release up to `limit` assets, for bundles too large to
+    /// withdraw at once
+    WithdrawBatch {
+        bundle_id: String,
+        limit: u32,
+    },
+    /// Refuse withdrawals from the bundle until `until`, the bundle itself can still be
+    /// transferred. An active lock can only be extended.
+    Lock {
+        bundle_id: String,
+        until: Expiration,
+    },
+    /// Permanently refuse withdrawals from and deposits into the bundle
+    Seal {
+        bundle_id: String,
+    },
+    /// Owner only, grant or revoke the right to deposit into the bundle. Deposit rights
+    /// are reset when the bundle changes hands.
+    UpdateDepositors {
+        bundle_id: String,
+        #[serde(default)]
+        add: Vec<String>,
+        #[serde(default)]
+        remove: Vec<String>,
+    },
+    /// Owner only, let anyone deposit into the bundle
+    SetOpenToContributions {
+        bundle_id: String,
+        open: bool,
+    },
+    /// Release every asset in the bundle to `recipient`, or the sender if unset,
+    /// and burn the bundle token
+    Unbundle {
+        bundle_id: String,
+        recipient: Option<String>,
+    },
+    /// Withdraw only the listed assets, fungible amounts may be partial but not zero
+    WithdrawAssets {
+        bundle_id: String,
+        #[serde(default)]
+        native: Vec<Coin>,
+        #[serde(default)]
+        cw20: Vec<Cw20Asset>,
+        #[serde(default)]
+        cw721: Vec<Cw721Asset>,
+        #[serde(default)]
+        cw1155: Vec<Cw1155Asset>,
+        /// Drop the listed assets from the bundle without transferring them, e.g. tokens
+        /// whose contract refuses transfers and would block every other withdrawal.
+        /// Discarded tokens stay with this contract.
+        #[serde(default)]
+        discard: bool,
+    },
+    /// Pull the listed assets from the sender into the bundle. The contract must be
+    /// allowed to move them: a cw20 allowance, a cw721 approval or a cw1155 approval.
+    DepositFromApprovals {
+        bundle_id: String,
+        #[serde(default)]
+        cw20: Vec<Cw20Asset>,
+        #[serde(default)]
+        cw721: Vec<Cw721Asset>,
+        #[serde(default)]
+        cw1155: Vec<Cw1155Asset>,
+    },
+    /// Admin only, changes every field that is set
+    UpdateConfig {
+        mint_policy: Option<MintPolicy>,
+        max_nesting_depth: Option<u32>,
+        max_bundle_assets: Option<u32>,
+    },
+    /// Admin or pauser, refuse deposits and mints. Bundle owners can still withdraw.
+    Pause {},
+    /// Admin or pauser, accept deposits and mints again
+    Unpause {},
+    /// Admin only, grant a role to an address
+    GrantRole {
+        role: Role,
+        address: String,
+    },
+    /// Admin only, revoke a role from an address
+    RevokeRole {
+        role: Role,
+        address: String,
+    },
+    /// Admin only, propose a new admin that takes over once it accepts
+    ProposeNewAdmin {
+        admin: String,
+    },
+    /// Proposed admin only, take over the admin role
+    AcceptAdmin {},
+    /// Admin only, withdraw the pending admin proposal
+    CancelAdminTransfer {},
+    /// Minter or admin, propose a new cw721 minter that takes over once it accepts. The
+    /// admin can also rotate a leaked minter key this way.
+    ProposeNewMinter {
+        minter: String,
+    },
+    /// Proposed minter only, take over the minter role
+    AcceptMinter {},
+    /// Minter or admin, withdraw the pending minter proposal
+    CancelMinterTransfer {},
+    /// Admin or allowlist manager, set how the asset list applies and add or remove asset contracts.
+    /// The list starts out as an empty allowlist.
+    UpdateAssetList {
+        filter: Option<AssetFilter>,
+        #[serde(default)]
+        add: Vec<String>,
+        #[serde(default)]
+        remove: Vec<String>,
+    },
+    /// Admin or fee manager, replaces the fee settings. Fees need a treasury to be paid to.
+    UpdateFees {
+        treasury: Option<String>,
+        mint_price: Option<Price>,
+        #[serde(default)]
+        withdraw_fee_bps: u16,
+    },
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
@@ -90,11 +414,163 @@ pub enum QueryMsg {
 
     // Return the minter
     Minter {},
+
+    /// Returns the contract configuration: `Config`
+    Config {},
+    /// Returns the fee settings: `FeeConfig`
+    Fees {},
+    /// Returns whether deposits and mints are paused: `PausedResponse`
+    Paused {},
+    /// Lists the holders of a role: `RoleMembersResponse`
+    RoleMembers {
+        role: Role,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns the proposed admin and minter: `PendingTransfersResponse`
+    PendingTransfers {},
+    /// Returns the asset filter and the listed asset contracts: `AssetListResponse`
+    AssetList {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns the lock of the bundle: `BundleLockResponse`
+    BundleLock {
+        bundle_id: String,
+    },
+
+    /// Returns who besides the owner may deposit into the bundle: `BundleDepositorsResponse`
+    BundleDepositors {
+        bundle_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Lists the addresses that deposited into the bundle: `BundleContributorsResponse`
+    BundleContributors {
+        bundle_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Returns every asset held by the bundle: `BundleContentsResponse`
+    BundleContents {
+        bundle_id: String,
+        /// set to true to also list the contents of nested bundles
+        recursive: Option<bool>,
+    },
+    /// Returns the result of both `AllNftInfo` and `BundleContents` as one query
+    /// for clients: `BundleInfoResponse`
+    BundleInfo {
+        bundle_id: String,
+        /// unset or false will filter out expired approvals, you must set to true to see them
+        include_expired: Option<bool>,
+    },
+    /// Returns `BundleInfo` for each of the given bundles, at most 30 at once:
+    /// `BundlesInfoResponse`
+    BundlesInfo {
+        bundle_ids: Vec<String>,
+        /// unset or false will filter out expired approvals, you must set to true to see them
+        include_expired: Option<bool>,
+    },
+    /// Lists the bundles holding tokens of the given contract. Set `token_id` to look up
+    /// a cw721 or cw1155 token, leave it unset to look up a cw20 token.
+    /// Return type: `BundlesResponse`
+    BundlesContaining {
+        contract_address: String,
+        token_id: Option<String>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BundlesResponse {
+    pub bundle_ids: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BundleContentsResponse {
+    pub native: Vec<Coin>,
+    pub cw20: Vec<CW20Wrapper>,
+    pub cw721: Vec<CW721Wrapper>,
+    pub cw1155: Vec<CW1155Wrapper>,
+    /// Contents of the bundles held in `cw721`, only filled in for recursive queries
+    #[serde(default)]
+    pub nested: Vec<NestedBundle>,
 }
 
-impl From<QueryMsg> for cw721_base::QueryMsg {
-    fn from(msg: QueryMsg) -> Self {
-        match msg {
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NestedBundle {
+    pub bundle_id: String,
+    pub contents: BundleContentsResponse,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BundleInfoResponse {
+    pub bundle_id: String,
+    /// Ownership and metadata of the bundle token
+    pub info: AllNftInfoResponse<Extension>,
+    pub contents: BundleContentsResponse,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BundleLockResponse {
+    pub lock: Option<BundleLock>,
+    /// Whether withdrawals are currently refused
+    pub active: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PausedResponse {
+    pub paused: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoleMembersResponse {
+    pub members: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingTransfersResponse {
+    pub admin: Option<Addr>,
+    pub minter: Option<Addr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AssetListResponse {
+    pub filter: AssetFilter,
+    pub contracts: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BundleDepositorsResponse {
+    /// Anyone may deposit
+    pub open: bool,
+    pub depositors: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Contributor {
+    pub address: String,
+    /// Number of deposits made into the bundle
+    pub deposits: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BundleContributorsResponse {
+    pub contributors: Vec<Contributor>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BundlesInfoResponse {
+    pub bundles: Vec<BundleInfoResponse>,
+}
+
+impl TryFrom<QueryMsg> for cw721_base::QueryMsg {
+    type Error = StdError;
+
+    fn try_from(msg: QueryMsg) -> Result<Self, Self::Error> {
+        let msg = match msg {
             QueryMsg::OwnerOf {
                 token_id,
                 include_expired,
@@ -136,6 +612,8 @@ impl From<QueryMsg> for cw721_base::QueryMsg {
                 cw721_base::QueryMsg::AllTokens { start_after, limit }
             }
             QueryMsg::Minter {} => cw721_base::QueryMsg::Minter {},
-        }
+            _ => return Err(StdError::generic_err("Not a cw721 query")),
+        };
+        Ok(msg)
     }
 }
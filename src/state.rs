@@ -1,15 +1,286 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::Addr;
-use cw_storage_plus::Item;
+use cosmwasm_std::{Addr, Coin, Empty, StdError, StdResult, Uint128};
+use cw721::Expiration;
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex, U64Key};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct State {
-    pub count: i32,
-    pub owner: Addr,
-    pub name: String,
-    pub symbol: String,
+pub struct Config {
+    /// May update this config
+    pub admin: Addr,
+    pub mint_policy: MintPolicy,
+    pub fees: FeeConfig,
+    /// Deposits and mints are refused while set, withdrawals keep working
+    pub paused: bool,
+    /// Most levels of bundles that may be nested below a top level bundle
+    #[serde(default = "default_max_nesting_depth")]
+    pub max_nesting_depth: u32,
+    /// Most distinct assets a bundle may hold, keeping withdrawals within gas limits
+    #[serde(default = "default_max_bundle_assets")]
+    pub max_bundle_assets: u32,
+    /// How `ASSET_LIST` is applied to deposited assets
+    #[serde(default)]
+    pub asset_filter: AssetFilter,
 }
 
-pub const STATE: Item<State> = Item::new("state");
+pub const DEFAULT_MAX_NESTING_DEPTH: u32 = 5;
+
+fn default_max_nesting_depth() -> u32 {
+    DEFAULT_MAX_NESTING_DEPTH
+}
+
+pub const DEFAULT_MAX_BUNDLE_ASSETS: u32 = 100;
+
+fn default_max_bundle_assets() -> u32 {
+    DEFAULT_MAX_BUNDLE_ASSETS
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MintPolicy {
+    /// Only the cw721 minter may mint bundles
+    Minter {},
+    /// Anyone may also mint bundles for themselves with `CreateBundle`, optionally
+    /// capped per owner
+    Public { max_per_owner: Option<u32> },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetFilter {
+    /// Assets of any contract can be deposited
+    Off {},
+    /// Only assets of listed contracts can be deposited
+    Allowlist {},
+    /// Assets of listed contracts cannot be deposited
+    Denylist {},
+}
+
+/// Hooks can be faked by any contract, so only listed asset contracts are trusted until
+/// the asset list says otherwise
+impl Default for AssetFilter {
+    fn default() -> Self {
+        AssetFilter::Allowlist {}
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct FeeConfig {
+    /// Receives protocol fees, required for any fee to be set
+    pub treasury: Option<Addr>,
+    /// Charged for every bundle minted
+    #[serde(default)]
+    pub mint_price: Option<MintPrice>,
+    /// Share of native, cw20 and cw1155 amounts leaving a bundle, in basis points
+    #[serde(default)]
+    pub withdraw_fee_bps: u16,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MintPrice {
+    /// Attached to the mint message
+    Native(Coin),
+    /// Taken from the payer's allowance to this contract
+    Cw20(CW20Wrapper),
+}
+
+pub const CONFIG: Item<Config> = Item::new("config");
+/// Operational roles the admin grants, so no single key has to hold every permission
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// May mint bundles besides the cw721 minter
+    Minter,
+    /// May pause and unpause the contract
+    Pauser,
+    /// May update the fees
+    FeeManager,
+    /// May update the asset list
+    AllowlistManager,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Minter => "minter",
+            Role::Pauser => "pauser",
+            Role::FeeManager => "fee_manager",
+            Role::AllowlistManager => "allowlist_manager",
+        }
+    }
+}
+
+/// Role holders keyed by (role, address)
+pub const ROLES: Map<(&str, &Addr), Empty> = Map::new("roles");
+
+/// Proposed admin, takes over once it accepts
+pub const PENDING_ADMIN: Item<Addr> = Item::new("pending_admin");
+/// Proposed cw721 minter, takes over once it accepts
+pub const PENDING_MINTER: Item<Addr> = Item::new("pending_minter");
+/// Asset contracts allowed or denied, depending on `Config::asset_filter`
+pub const ASSET_LIST: Map<&Addr, Empty> = Map::new("asset_list");
+
+// Bundled assets are stored one entry per asset, keyed by bundle id first so a whole
+// bundle can be iterated with `prefix(bundle_id)`. Fungible amounts are summed per key.
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CW20Wrapper {
+    pub contract_address: Addr,
+    pub amount: Uint128,
+}
+
+pub struct Cw20BundleIndexes<'a> {
+    // pk goes to second tuple element
+    pub contract: MultiIndex<'a, (Addr, Vec<u8>), CW20Wrapper>,
+}
+
+impl<'a> IndexList<CW20Wrapper> for Cw20BundleIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<CW20Wrapper>> + '_> {
+        let v: Vec<&dyn Index<CW20Wrapper>> = vec![&self.contract];
+        Box::new(v.into_iter())
+    }
+}
+
+/// cw20 tokens keyed by (bundle_id, contract_address)
+pub fn cw20_bundle<'a>() -> IndexedMap<'a, (&'a str, &'a Addr), CW20Wrapper, Cw20BundleIndexes<'a>>
+{
+    let indexes = Cw20BundleIndexes {
+        contract: MultiIndex::new(
+            |d: &CW20Wrapper, k: Vec<u8>| (d.contract_address.clone(), k),
+            "cw20_assets",
+            "cw20_assets__contract",
+        ),
+    };
+    IndexedMap::new("cw20_assets", indexes)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CW721Wrapper {
+    pub contract_address: Addr,
+    pub token_id: String,
+}
+
+pub struct Cw721BundleIndexes<'a> {
+    // pk goes to third tuple element
+    pub token: MultiIndex<'a, (Addr, String, Vec<u8>), CW721Wrapper>,
+}
+
+impl<'a> IndexList<CW721Wrapper> for Cw721BundleIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<CW721Wrapper>> + '_> {
+        let v: Vec<&dyn Index<CW721Wrapper>> = vec![&self.token];
+        Box::new(v.into_iter())
+    }
+}
+
+/// cw721 tokens keyed by (bundle_id, contract_address, token_id)
+pub fn cw721_bundle<'a>(
+) -> IndexedMap<'a, (&'a str, &'a Addr, &'a str), CW721Wrapper, Cw721BundleIndexes<'a>> {
+    let indexes = Cw721BundleIndexes {
+        token: MultiIndex::new(
+            |d: &CW721Wrapper, k: Vec<u8>| (d.contract_address.clone(), d.token_id.clone(), k),
+            "cw721_assets",
+            "cw721_assets__token",
+        ),
+    };
+    IndexedMap::new("cw721_assets", indexes)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CW1155Wrapper {
+    pub contract_address: Addr,
+    pub token_id: String,
+    pub amount: Uint128,
+}
+
+pub struct Cw1155BundleIndexes<'a> {
+    // pk goes to third tuple element
+    pub token: MultiIndex<'a, (Addr, String, Vec<u8>), CW1155Wrapper>,
+}
+
+impl<'a> IndexList<CW1155Wrapper> for Cw1155BundleIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<CW1155Wrapper>> + '_> {
+        let v: Vec<&dyn Index<CW1155Wrapper>> = vec![&self.token];
+        Box::new(v.into_iter())
+    }
+}
+
+/// cw1155 tokens keyed by (bundle_id, contract_address, token_id)
+pub fn cw1155_bundle<'a>(
+) -> IndexedMap<'a, (&'a str, &'a Addr, &'a str), CW1155Wrapper, Cw1155BundleIndexes<'a>> {
+    let indexes = Cw1155BundleIndexes {
+        token: MultiIndex::new(
+            |d: &CW1155Wrapper, k: Vec<u8>| (d.contract_address.clone(), d.token_id.clone(), k),
+            "cw1155_assets",
+            "cw1155_assets__token",
+        ),
+    };
+    IndexedMap::new("cw1155_assets", indexes)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BundleLock {
+    /// Withdrawals are refused until the expiration
+    Until(Expiration),
+    /// Withdrawals and deposits are refused for good
+    Sealed {},
+}
+
+/// Locked and sealed bundles
+pub const LOCKS: Map<&str, BundleLock> = Map::new("bundle_locks");
+
+/// Addresses besides the owner that may deposit into a bundle, keyed by (bundle_id, depositor)
+pub const ALLOWED_DEPOSITORS: Map<(&str, &Addr), Empty> = Map::new("allowed_depositors");
+/// Bundles anyone may deposit into
+pub const OPEN_BUNDLES: Map<&str, Empty> = Map::new("open_bundles");
+/// Number of deposits each address has made into a bundle, keyed by (bundle_id, depositor)
+pub const CONTRIBUTORS: Map<(&str, &Addr), u32> = Map::new("bundle_contributors");
+
+/// Last numeric id handed out by `CreateBundle`
+pub const BUNDLE_COUNT: Item<u64> = Item::new("bundle_count");
+/// Bundles each address has minted with `CreateBundle`
+pub const CREATED_BUNDLES: Map<&Addr, u32> = Map::new("created_bundles");
+/// Creator of each bundle counted in `CREATED_BUNDLES`, so unbundling frees its place
+pub const BUNDLE_CREATORS: Map<&str, Addr> = Map::new("bundle_creators");
+
+/// Native coin amounts keyed by (bundle_id, denom)
+pub const NATIVE_BUNDLE: Map<(&str, &str), Uint128> = Map::new("native_assets");
+
+/// Distinct assets each bundle holds, so its size is checked without loading it
+pub const ASSET_COUNTS: Map<&str, u32> = Map::new("bundle_asset_counts");
+
+/// An asset pulled by `DepositFromApprovals`, recorded once its transfer succeeds
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PendingAsset {
+    Cw20(CW20Wrapper),
+    Cw721(CW721Wrapper),
+    Cw1155(CW1155Wrapper),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingDeposit {
+    pub bundle_id: String,
+    pub depositor: Addr,
+    pub asset: PendingAsset,
+}
+
+/// Pending deposits keyed by the reply id of their transfer
+pub const PENDING_DEPOSITS: Map<U64Key, PendingDeposit> = Map::new("pending_deposits");
+/// Last reply id handed out to a pending deposit
+pub const PENDING_DEPOSIT_ID: Item<u64> = Item::new("pending_deposit_id");
+
+/// Recover the bundle id from the primary key of a bundled asset, where it is
+/// stored length-prefixed as the first element
+pub fn bundle_id_from_key(key: &[u8]) -> StdResult<String> {
+    if key.len() < 2 {
+        return Err(StdError::generic_err("Invalid bundle asset key"));
+    }
+    let len = u16::from_be_bytes([key[0], key[1]]) as usize;
+    let bundle_id = key
+        .get(2..2 + len)
+        .ok_or_else(|| StdError::generic_err("Invalid bundle asset key"))?;
+    String::from_utf8(bundle_id.to_vec()).map_err(StdError::from)
+}